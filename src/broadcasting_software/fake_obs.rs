@@ -0,0 +1,801 @@
+//! A local server that speaks the obs-websocket 4.x or 5.x protocol or the
+//! Streamlabs Desktop JSON-RPC API, implements the requests used by
+//! [`super::obs::Obs`], [`super::obs_v5::ObsV5`] and [`super::slobs::Slobs`]
+//! and sends the events OBS would send. Used to test the connections
+//! without running OBS
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::{
+    mpsc::{self, UnboundedSender},
+    Notify,
+};
+use warp::{
+    ws::{Message, WebSocket},
+    Filter,
+};
+
+use crate::{config, noalbs, state::State};
+
+use super::obs_v5;
+
+pub struct FakeObs {
+    addr: SocketAddr,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Clone)]
+enum Protocol {
+    V4,
+    V5 { password: Option<String> },
+    Slobs { token: Option<String> },
+}
+
+struct Inner {
+    protocol: Protocol,
+    scenes: Vec<String>,
+    current_scene: String,
+    streaming: bool,
+    recording: bool,
+    media_sources: Vec<MediaSource>,
+
+    /// Refuse new connections like OBS that isn't running
+    accepting: bool,
+
+    /// Amount of accepted connections
+    connections: usize,
+
+    /// Type and data of every request that got received
+    received: Vec<(String, Value)>,
+
+    /// Identified clients that receive the events
+    clients: Vec<UnboundedSender<Message>>,
+
+    /// Notified when a connection got refused because of accepting
+    refused: Arc<Notify>,
+
+    /// Notified when a connection closed
+    closed: Arc<Notify>,
+}
+
+struct MediaSource {
+    name: String,
+    kind: String,
+    settings: Value,
+    playing: bool,
+}
+
+enum Event {
+    SceneChanged(String),
+    StreamStarted,
+    StreamStopped,
+}
+
+const SALT: &str = "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=";
+const CHALLENGE: &str = "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY=";
+
+impl FakeObs {
+    /// Starts an obs-websocket 4.x server on a random port, the first scene
+    /// is the current scene
+    pub async fn start(scenes: &[&str]) -> Self {
+        Self::start_with(Protocol::V4, scenes)
+    }
+
+    /// Starts an obs-websocket 5.x server that requires the password when
+    /// set
+    pub async fn start_v5(scenes: &[&str], password: Option<&str>) -> Self {
+        let password = password.map(str::to_owned);
+
+        Self::start_with(Protocol::V5 { password }, scenes)
+    }
+
+    /// Starts a Streamlabs Desktop API server that requires the token when
+    /// set
+    pub async fn start_slobs(scenes: &[&str], token: Option<&str>) -> Self {
+        let token = token.map(str::to_owned);
+
+        Self::start_with(Protocol::Slobs { token }, scenes)
+    }
+
+    fn start_with(protocol: Protocol, scenes: &[&str]) -> Self {
+        let inner = Arc::new(Mutex::new(Inner {
+            protocol,
+            scenes: scenes.iter().map(|s| s.to_string()).collect(),
+            current_scene: scenes.first().unwrap_or(&"").to_string(),
+            streaming: false,
+            recording: false,
+            media_sources: Vec::new(),
+            accepting: true,
+            connections: 0,
+            received: Vec::new(),
+            clients: Vec::new(),
+            refused: Arc::new(Notify::new()),
+            closed: Arc::new(Notify::new()),
+        }));
+
+        let route_inner = inner.clone();
+        let route = warp::ws().map(move |ws: warp::ws::Ws| -> Box<dyn warp::Reply> {
+            {
+                let inner = route_inner.lock().unwrap();
+
+                if !inner.accepting {
+                    inner.refused.notify_waiters();
+                    return Box::new(warp::http::StatusCode::SERVICE_UNAVAILABLE);
+                }
+            }
+
+            let inner = route_inner.clone();
+            Box::new(ws.on_upgrade(move |socket| connected(socket, inner)))
+        });
+
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        Self { addr, inner }
+    }
+
+    pub fn config(&self) -> config::ObsConfig {
+        config::ObsConfig {
+            host: self.addr.ip().to_string(),
+            password: None,
+            port: self.addr.port(),
+        }
+    }
+
+    pub fn current_scene(&self) -> String {
+        self.inner.lock().unwrap().current_scene.to_owned()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.inner.lock().unwrap().connections
+    }
+
+    /// Data of the requests of this type that got received
+    pub fn received(&self, request_type: &str) -> Vec<Value> {
+        self.inner
+            .lock()
+            .unwrap()
+            .received
+            .iter()
+            .filter(|(received_type, _)| received_type == request_type)
+            .map(|(_, data)| data.to_owned())
+            .collect()
+    }
+
+    /// Adds a playing media source
+    pub fn add_media_source(&self, name: &str, kind: &str, settings: Value) {
+        self.inner.lock().unwrap().media_sources.push(MediaSource {
+            name: name.to_owned(),
+            kind: kind.to_owned(),
+            settings,
+            playing: true,
+        });
+    }
+
+    /// Switches the scene like a user would in OBS
+    pub fn switch_scene(&self, scene: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.current_scene = scene.to_owned();
+        inner.broadcast(Event::SceneChanged(scene.to_owned()));
+    }
+
+    /// Starts streaming like a user would in OBS
+    pub fn start_streaming(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.streaming = true;
+        inner.broadcast(Event::StreamStarted);
+    }
+
+    pub fn refused_notifier(&self) -> Arc<Notify> {
+        self.inner.lock().unwrap().refused.clone()
+    }
+
+    pub fn closed_notifier(&self) -> Arc<Notify> {
+        self.inner.lock().unwrap().closed.clone()
+    }
+
+    pub fn set_accepting(&self, accepting: bool) {
+        self.inner.lock().unwrap().accepting = accepting;
+    }
+
+    /// Closes all connections like OBS shutting down
+    pub fn disconnect(&self) {
+        for client in self.inner.lock().unwrap().clients.drain(..) {
+            let _ = client.send(Message::close());
+        }
+    }
+}
+
+/// State of a user with the default config that isn't connected yet
+pub fn user_state() -> noalbs::UserState {
+    let config = serde_json::from_value(json!({
+        "user": { "name": "test" },
+        "switcher": {},
+        "software": { "type": "Obs", "host": "localhost", "port": 4444 },
+        "optionalScenes": {},
+        "optionalOptions": {}
+    }))
+    .unwrap();
+
+    let mut state = State {
+        config,
+        switcher_state: Default::default(),
+        broadcasting_software: Default::default(),
+        event_senders: Vec::new(),
+    };
+
+    state.set_all_switchable_scenes();
+
+    Arc::new(tokio::sync::RwLock::new(state))
+}
+
+impl Inner {
+    fn broadcast(&mut self, event: Event) {
+        let text = self.encode(event).to_string();
+
+        self.clients
+            .retain(|client| client.send(Message::text(text.to_owned())).is_ok());
+    }
+
+    fn encode(&self, event: Event) -> Value {
+        match self.protocol {
+            Protocol::V4 => match event {
+                Event::SceneChanged(scene) => json!({
+                    "update-type": "SwitchScenes",
+                    "scene-name": scene,
+                    "sources": []
+                }),
+                Event::StreamStarted => json!({ "update-type": "StreamStarted" }),
+                Event::StreamStopped => json!({ "update-type": "StreamStopped" }),
+            },
+            Protocol::V5 { .. } => {
+                let (event_type, event_data) = match event {
+                    Event::SceneChanged(scene) => {
+                        ("CurrentProgramSceneChanged", json!({ "sceneName": scene }))
+                    }
+                    Event::StreamStarted => (
+                        "StreamStateChanged",
+                        json!({ "outputActive": true, "outputState": "OBS_WEBSOCKET_OUTPUT_STARTED" }),
+                    ),
+                    Event::StreamStopped => (
+                        "StreamStateChanged",
+                        json!({ "outputActive": false, "outputState": "OBS_WEBSOCKET_OUTPUT_STOPPED" }),
+                    ),
+                };
+
+                json!({
+                    "op": 5,
+                    "d": {
+                        "eventType": event_type,
+                        "eventIntent": 1,
+                        "eventData": event_data
+                    }
+                })
+            }
+            Protocol::Slobs { .. } => {
+                let (resource_id, data) = match event {
+                    Event::SceneChanged(scene) => (
+                        "ScenesService.sceneSwitched",
+                        json!({ "id": self.scene_id(&scene), "name": scene }),
+                    ),
+                    Event::StreamStarted => {
+                        ("StreamingService.streamingStatusChange", json!("live"))
+                    }
+                    Event::StreamStopped => {
+                        ("StreamingService.streamingStatusChange", json!("offline"))
+                    }
+                };
+
+                json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "result": {
+                        "_type": "EVENT",
+                        "resourceId": resource_id,
+                        "emitter": "STREAM",
+                        "data": data
+                    }
+                })
+            }
+        }
+    }
+
+    fn scene_id(&self, scene: &str) -> String {
+        let index = self.scenes.iter().position(|s| s == scene);
+
+        format!("scene-{}", index.unwrap_or_default())
+    }
+
+    fn media_source(&mut self, name: &str) -> Option<&mut MediaSource> {
+        self.media_sources.iter_mut().find(|m| m.name == name)
+    }
+
+    fn set_scene(&mut self, scene: &str, events: &mut Vec<Event>) -> Result<(), &'static str> {
+        if !self.scenes.iter().any(|s| s == scene) {
+            return Err("requested scene does not exist");
+        }
+
+        self.current_scene = scene.to_owned();
+        events.push(Event::SceneChanged(scene.to_owned()));
+
+        Ok(())
+    }
+
+    /// The response data of a 4.x request and the events it caused
+    fn respond_v4(
+        &mut self,
+        request: &Value,
+        events: &mut Vec<Event>,
+    ) -> Result<Value, &'static str> {
+        let request_type = request["request-type"].as_str().unwrap_or_default();
+        self.received
+            .push((request_type.to_owned(), request.to_owned()));
+
+        let source_name = request["sourceName"].as_str().unwrap_or_default();
+
+        Ok(match request_type {
+            "GetVersion" => json!({
+                "version": 1.1,
+                "obs-websocket-version": "4.9.1",
+                "obs-studio-version": "27.2.0",
+                "available-requests": "GetVersion,GetAuthRequired,GetSceneList,SetCurrentScene,GetStreamingStatus,StartStreaming,StopStreaming,StartStopRecording,GetRecordingStatus,GetMediaSourcesList,GetSourceSettings,StopMedia,PlayPauseMedia",
+                "supported-image-export-formats": "png,jpg"
+            }),
+            "GetAuthRequired" => json!({ "authRequired": false }),
+            "GetSceneList" => json!({
+                "current-scene": self.current_scene,
+                "scenes": self
+                    .scenes
+                    .iter()
+                    .map(|name| json!({ "name": name, "sources": [] }))
+                    .collect::<Vec<_>>()
+            }),
+            "SetCurrentScene" => {
+                let scene = request["scene-name"].as_str().unwrap_or_default();
+                self.set_scene(scene, events)?;
+                json!({})
+            }
+            "GetStreamingStatus" => json!({
+                "streaming": self.streaming,
+                "recording": self.recording,
+                "recording-paused": false,
+                "virtualcam": false,
+                "preview-only": false
+            }),
+            "StartStreaming" => {
+                self.streaming = true;
+                events.push(Event::StreamStarted);
+                json!({})
+            }
+            "StopStreaming" => {
+                self.streaming = false;
+                events.push(Event::StreamStopped);
+                json!({})
+            }
+            "StartStopRecording" => {
+                self.recording = !self.recording;
+                json!({})
+            }
+            "GetRecordingStatus" => json!({
+                "isRecording": self.recording,
+                "isRecordingPaused": false
+            }),
+            "GetMediaSourcesList" => json!({
+                "mediaSources": self
+                    .media_sources
+                    .iter()
+                    .map(|m| json!({
+                        "sourceName": m.name,
+                        "sourceKind": m.kind,
+                        "mediaState": if m.playing { "playing" } else { "stopped" }
+                    }))
+                    .collect::<Vec<_>>()
+            }),
+            "GetSourceSettings" => {
+                let source = self
+                    .media_source(source_name)
+                    .ok_or("specified source doesn't exist")?;
+
+                json!({
+                    "sourceName": source.name,
+                    "sourceType": source.kind,
+                    "sourceSettings": source.settings
+                })
+            }
+            "StopMedia" => {
+                let source = self
+                    .media_source(source_name)
+                    .ok_or("specified source doesn't exist")?;
+
+                source.playing = false;
+                json!({})
+            }
+            "PlayPauseMedia" => {
+                let source = self
+                    .media_source(source_name)
+                    .ok_or("specified source doesn't exist")?;
+
+                source.playing = !request["playPause"].as_bool().unwrap_or_default();
+                json!({})
+            }
+            _ => return Err("invalid request type"),
+        })
+    }
+
+    /// The response data of a 5.x request and the events it caused
+    fn respond_v5(
+        &mut self,
+        request_type: &str,
+        data: &Value,
+        events: &mut Vec<Event>,
+    ) -> Result<Value, &'static str> {
+        self.received
+            .push((request_type.to_owned(), data.to_owned()));
+
+        let input_name = data["inputName"].as_str().unwrap_or_default();
+
+        Ok(match request_type {
+            "GetSceneList" => json!({
+                "currentProgramSceneName": self.current_scene,
+                "currentPreviewSceneName": null,
+                "scenes": self
+                    .scenes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| json!({ "sceneName": name, "sceneIndex": index }))
+                    .collect::<Vec<_>>()
+            }),
+            "SetCurrentProgramScene" => {
+                let scene = data["sceneName"].as_str().unwrap_or_default();
+                self.set_scene(scene, events)?;
+                Value::Null
+            }
+            "GetStreamStatus" => {
+                let (duration, bytes, skipped, total) = if self.streaming {
+                    (60_000, 45_000_000, 12, 3600)
+                } else {
+                    (0, 0, 0, 0)
+                };
+
+                json!({
+                    "outputActive": self.streaming,
+                    "outputReconnecting": false,
+                    "outputTimecode": "00:01:00.000",
+                    "outputDuration": duration,
+                    "outputCongestion": 0.0,
+                    "outputBytes": bytes,
+                    "outputSkippedFrames": skipped,
+                    "outputTotalFrames": total
+                })
+            }
+            "GetStats" => json!({
+                "cpuUsage": 12.5,
+                "memoryUsage": 512.0,
+                "availableDiskSpace": 102400.0,
+                "activeFps": 60.0,
+                "averageFrameRenderTime": 1.2,
+                "renderSkippedFrames": 0,
+                "renderTotalFrames": 3600,
+                "outputSkippedFrames": 0,
+                "outputTotalFrames": 3600,
+                "webSocketSessionIncomingMessages": 0,
+                "webSocketSessionOutgoingMessages": 0
+            }),
+            "StartStream" => {
+                self.streaming = true;
+                events.push(Event::StreamStarted);
+                Value::Null
+            }
+            "StopStream" => {
+                self.streaming = false;
+                events.push(Event::StreamStopped);
+                Value::Null
+            }
+            "ToggleRecord" => {
+                self.recording = !self.recording;
+                json!({ "outputActive": self.recording })
+            }
+            "GetRecordStatus" => json!({
+                "outputActive": self.recording,
+                "outputPaused": false,
+                "outputDuration": if self.recording { 30_000 } else { 0 }
+            }),
+            "GetInputList" => json!({
+                "inputs": self
+                    .media_sources
+                    .iter()
+                    .map(|m| json!({
+                        "inputName": m.name,
+                        "inputKind": m.kind,
+                        "unversionedInputKind": m.kind
+                    }))
+                    .collect::<Vec<_>>()
+            }),
+            "GetMediaInputStatus" => {
+                let source = self.media_source(input_name).ok_or("No source was found")?;
+
+                let state = if source.playing {
+                    "OBS_MEDIA_STATE_PLAYING"
+                } else {
+                    "OBS_MEDIA_STATE_STOPPED"
+                };
+
+                json!({ "mediaState": state })
+            }
+            "GetInputSettings" => {
+                let source = self.media_source(input_name).ok_or("No source was found")?;
+
+                json!({
+                    "inputSettings": source.settings,
+                    "inputKind": source.kind
+                })
+            }
+            "TriggerMediaInputAction" => {
+                self.media_source(input_name).ok_or("No source was found")?;
+                Value::Null
+            }
+            _ => return Err("Your request type is not valid"),
+        })
+    }
+
+    /// The result of a Streamlabs Desktop request and the events it caused
+    fn respond_slobs(
+        &mut self,
+        method: &str,
+        params: &Value,
+        events: &mut Vec<Event>,
+    ) -> Result<Value, &'static str> {
+        self.received.push((method.to_owned(), params.to_owned()));
+
+        let resource = params["resource"].as_str().unwrap_or_default();
+        let args = &params["args"];
+
+        // Status changes a minute ago
+        let changed = (chrono::Utc::now() - chrono::Duration::minutes(1)).to_rfc3339();
+
+        let streaming_model = |streaming: bool, recording: bool| {
+            json!({
+                "streamingStatus": if streaming { "live" } else { "offline" },
+                "streamingStatusTime": changed,
+                "recordingStatus": if recording { "recording" } else { "offline" },
+                "recordingStatusTime": changed
+            })
+        };
+
+        Ok(match (resource, method) {
+            ("ScenesService", "getScenes") => json!(self
+                .scenes
+                .iter()
+                .map(|name| json!({
+                    "_type": "HELPER",
+                    "resourceId": format!("Scene[\"{}\"]", self.scene_id(name)),
+                    "id": self.scene_id(name),
+                    "name": name
+                }))
+                .collect::<Vec<_>>()),
+            ("ScenesService", "activeScene") => json!({
+                "id": self.scene_id(&self.current_scene),
+                "name": self.current_scene
+            }),
+            ("ScenesService", "makeSceneActive") => {
+                let id = args[0].as_str().unwrap_or_default();
+                let scene = self
+                    .scenes
+                    .iter()
+                    .find(|s| self.scene_id(s) == id)
+                    .ok_or("Scene not found")?
+                    .to_owned();
+
+                self.set_scene(&scene, events)?;
+                json!(true)
+            }
+            ("StreamingService", "getModel") => streaming_model(self.streaming, self.recording),
+            ("StreamingService", "toggleStreaming") => {
+                self.streaming = !self.streaming;
+                events.push(if self.streaming {
+                    Event::StreamStarted
+                } else {
+                    Event::StreamStopped
+                });
+                Value::Null
+            }
+            ("StreamingService", "toggleRecording") => {
+                self.recording = !self.recording;
+                Value::Null
+            }
+            ("PerformanceService", "getModel") => json!({
+                "CPU": 12.5,
+                "numberDroppedFrames": if self.streaming { 12 } else { 0 },
+                "percentageDroppedFrames": if self.streaming { 0.3 } else { 0.0 },
+                "streamingBandwidth": if self.streaming { 6000.0 } else { 0.0 },
+                "frameRate": 60.0
+            }),
+            ("SourcesService", "getSources") => json!(self
+                .media_sources
+                .iter()
+                .map(|m| json!({
+                    "_type": "HELPER",
+                    "resourceId": format!("Source[\"{}\"]", m.name),
+                    "sourceId": m.name,
+                    "name": m.name,
+                    "type": m.kind
+                }))
+                .collect::<Vec<_>>()),
+            (source, "getSettings" | "updateSettings") => {
+                let name = source
+                    .strip_prefix("Source[\"")
+                    .and_then(|s| s.strip_suffix("\"]"))
+                    .unwrap_or_default();
+                let source = self.media_source(name).ok_or("Source not found")?;
+
+                if method == "updateSettings" {
+                    return Ok(Value::Null);
+                }
+
+                source.settings.to_owned()
+            }
+            (service, "sceneSwitched" | "streamingStatusChange") => json!({
+                "_type": "SUBSCRIPTION",
+                "resourceId": format!("{}.{}", service, method),
+                "emitter": "STREAM"
+            }),
+            _ => return Err("Method not found"),
+        })
+    }
+}
+
+async fn connected(socket: WebSocket, inner: Arc<Mutex<Inner>>) {
+    let (mut socket_tx, mut socket_rx) = socket.split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Message>();
+
+    let protocol = {
+        let mut inner = inner.lock().unwrap();
+        inner.connections += 1;
+        inner.protocol.to_owned()
+    };
+
+    tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let close = message.is_close();
+
+            if socket_tx.send(message).await.is_err() || close {
+                return;
+            }
+        }
+    });
+
+    // Streamlabs Desktop only answers the auth request till authorized
+    let mut authorized = matches!(protocol, Protocol::Slobs { token: None });
+
+    match &protocol {
+        Protocol::V4 | Protocol::Slobs { .. } => inner.lock().unwrap().clients.push(tx.clone()),
+        Protocol::V5 { password } => {
+            let authentication = password
+                .as_ref()
+                .map(|_| json!({ "challenge": CHALLENGE, "salt": SALT }));
+
+            let hello = json!({
+                "op": 0,
+                "d": {
+                    "obsWebSocketVersion": "5.0.1",
+                    "rpcVersion": 1,
+                    "authentication": authentication
+                }
+            });
+
+            let _ = tx.send(Message::text(hello.to_string()));
+        }
+    }
+
+    while let Some(Ok(message)) = socket_rx.next().await {
+        let request: Value = match message.to_str().map(serde_json::from_str) {
+            Ok(Ok(request)) => request,
+            _ => continue,
+        };
+
+        let mut inner = inner.lock().unwrap();
+        let mut events = Vec::new();
+
+        let response = match &protocol {
+            Protocol::V4 => {
+                let mut response = match inner.respond_v4(&request, &mut events) {
+                    Ok(mut data) => {
+                        data["status"] = json!("ok");
+                        data
+                    }
+                    Err(e) => json!({ "status": "error", "error": e }),
+                };
+
+                response["message-id"] = request["message-id"].to_owned();
+                response
+            }
+            Protocol::V5 { password } => match request["op"].as_u64() {
+                Some(1) => {
+                    let expected = password
+                        .as_ref()
+                        .map(|password| obs_v5::auth_string(password, SALT, CHALLENGE));
+
+                    if expected.is_some()
+                        && request["d"]["authentication"].as_str() != expected.as_deref()
+                    {
+                        let _ = tx.send(Message::close_with(4009u16, "Authentication failed."));
+                        break;
+                    }
+
+                    inner.clients.push(tx.clone());
+                    json!({ "op": 2, "d": { "negotiatedRpcVersion": 1 } })
+                }
+                Some(6) => {
+                    let d = &request["d"];
+                    let request_type = d["requestType"].as_str().unwrap_or_default();
+                    let data = &d["requestData"];
+
+                    let (status, response_data) =
+                        match inner.respond_v5(request_type, data, &mut events) {
+                            Ok(response_data) => {
+                                (json!({ "result": true, "code": 100 }), response_data)
+                            }
+                            Err(e) => (
+                                json!({ "result": false, "code": 600, "comment": e }),
+                                Value::Null,
+                            ),
+                        };
+
+                    json!({
+                        "op": 7,
+                        "d": {
+                            "requestType": request_type,
+                            "requestId": d["requestId"],
+                            "requestStatus": status,
+                            "responseData": response_data
+                        }
+                    })
+                }
+                _ => continue,
+            },
+            Protocol::Slobs { token } => {
+                let method = request["method"].as_str().unwrap_or_default();
+                let params = &request["params"];
+
+                let result = if method == "auth" {
+                    authorized = params["args"][0].as_str() == token.as_deref();
+
+                    if authorized {
+                        Ok(json!(true))
+                    } else {
+                        Err("Invalid token")
+                    }
+                } else if !authorized {
+                    Err("Authorization required")
+                } else {
+                    inner.respond_slobs(method, params, &mut events)
+                };
+
+                match result {
+                    Ok(result) => {
+                        json!({ "jsonrpc": "2.0", "id": request["id"], "result": result })
+                    }
+                    Err(e) => json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": { "code": -32600, "message": e }
+                    }),
+                }
+            }
+        };
+
+        let _ = tx.send(Message::text(response.to_string()));
+
+        for event in events {
+            inner.broadcast(event);
+        }
+    }
+
+    let closed = inner.lock().unwrap().closed.clone();
+    closed.notify_waiters();
+}
@@ -0,0 +1,237 @@
+//! A local server that speaks the line protocol of the vMix TCP API,
+//! implements the functions used by [`super::vmix::Vmix`] and sends the
+//! activators vMix would send. Used to test the vMix connection without
+//! running vMix
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::mpsc::{self, UnboundedSender},
+};
+
+use crate::config;
+
+pub struct FakeVmix {
+    addr: SocketAddr,
+    inner: Arc<Mutex<Inner>>,
+}
+
+struct Inner {
+    inputs: Vec<String>,
+
+    /// Number of the input in program, starting at 1
+    active: usize,
+    streaming: bool,
+    recording: bool,
+
+    /// Every function that got received with its query
+    received: Vec<String>,
+
+    /// Subscribed clients that receive the activators
+    subscribers: Vec<UnboundedSender<String>>,
+}
+
+impl FakeVmix {
+    /// Starts the server on a random port, the first input is in program
+    pub async fn start(inputs: &[&str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let inner = Arc::new(Mutex::new(Inner {
+            inputs: inputs.iter().map(|i| i.to_string()).collect(),
+            active: 1,
+            streaming: false,
+            recording: false,
+            received: Vec::new(),
+            subscribers: Vec::new(),
+        }));
+
+        let accept_inner = inner.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(connected(stream, accept_inner.clone()));
+            }
+        });
+
+        Self { addr, inner }
+    }
+
+    pub fn config(&self) -> config::VmixConfig {
+        config::VmixConfig {
+            host: self.addr.ip().to_string(),
+            port: self.addr.port(),
+        }
+    }
+
+    pub fn active_input(&self) -> String {
+        let inner = self.inner.lock().unwrap();
+        inner.inputs[inner.active - 1].to_owned()
+    }
+
+    /// Functions with their query that got received, like
+    /// `CutDirect Input=live`
+    pub fn received_functions(&self) -> Vec<String> {
+        self.inner.lock().unwrap().received.to_owned()
+    }
+
+    /// Cuts to the input like a user would in vMix
+    pub fn cut(&self, input: &str) {
+        self.inner.lock().unwrap().cut(input);
+    }
+}
+
+impl Inner {
+    fn broadcast(&mut self, activator: &str) {
+        let line = format!("ACTS OK {}\r\n", activator);
+        self.subscribers.retain(|s| s.send(line.to_owned()).is_ok());
+    }
+
+    /// Inputs can be given by title or number
+    fn cut(&mut self, input: &str) -> bool {
+        let number = match self.inputs.iter().position(|i| i == input) {
+            Some(index) => index + 1,
+            None => match input.parse() {
+                Ok(number) if (1..=self.inputs.len()).contains(&number) => number,
+                _ => return false,
+            },
+        };
+
+        let prev = std::mem::replace(&mut self.active, number);
+        self.broadcast(&format!("Input {} 0", prev));
+        self.broadcast(&format!("Input {} 1", number));
+
+        true
+    }
+
+    fn xml(&self) -> String {
+        let inputs: String = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, title)| {
+                format!(
+                    r#"<input key="key-{n}" number="{n}" type="Capture" title="{t}" state="Running">{t}</input>"#,
+                    n = index + 1,
+                    t = title
+                )
+            })
+            .collect();
+
+        let flag = |on: bool| if on { "True" } else { "False" };
+
+        format!(
+            "<vmix><version>25.0.0.34</version><edition>HD</edition><inputs>{}</inputs><active>{}</active><preview>1</preview><recording>{}</recording><streaming>{}</streaming></vmix>",
+            inputs,
+            self.active,
+            flag(self.recording),
+            flag(self.streaming)
+        )
+    }
+
+    fn function(&mut self, function: &str, query: &str) -> String {
+        let decoded = decode(query);
+        self.received
+            .push(format!("{} {}", function, decoded).trim_end().to_owned());
+
+        let input = decoded.strip_prefix("Input=").unwrap_or_default();
+
+        let ok = match function {
+            "CutDirect" => self.cut(input),
+            "StartStreaming" => {
+                self.streaming = true;
+                self.broadcast("Streaming 1");
+                true
+            }
+            "StopStreaming" => {
+                self.streaming = false;
+                self.broadcast("Streaming 0");
+                true
+            }
+            "StartStopRecording" => {
+                self.recording = !self.recording;
+                true
+            }
+            "Restart" => true,
+            _ => false,
+        };
+
+        if ok {
+            "FUNCTION OK Completed".to_string()
+        } else {
+            "FUNCTION ER Unknown function or input".to_string()
+        }
+    }
+}
+
+async fn connected(stream: tokio::net::TcpStream, inner: Arc<Mutex<Inner>>) {
+    let (reader, mut writer) = stream.into_split();
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+
+    tokio::spawn(async move {
+        while let Some(text) = rx.recv().await {
+            if writer.write_all(text.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let _ = tx.send("VERSION OK 25.0.0.34\r\n".to_string());
+
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let mut parts = line.trim().splitn(3, ' ');
+
+        let response = {
+            let mut inner = inner.lock().unwrap();
+
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("SUBSCRIBE"), Some("ACTS"), _) => {
+                    inner.subscribers.push(tx.clone());
+                    "SUBSCRIBE OK ACTS\r\n".to_string()
+                }
+                (Some("XML"), None, _) => {
+                    let xml = inner.xml();
+                    format!("XML {}\r\n{}\r\n", xml.len(), xml)
+                }
+                (Some("FUNCTION"), Some(function), query) => {
+                    format!(
+                        "{}\r\n",
+                        inner.function(function, query.unwrap_or_default())
+                    )
+                }
+                _ => "ER Unknown command\r\n".to_string(),
+            }
+        };
+
+        let _ = tx.send(response);
+    }
+}
+
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = String::from_utf8_lossy(&bytes[i + 1..i + 3]);
+
+            if let Ok(b) = u8::from_str_radix(&hex, 16) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
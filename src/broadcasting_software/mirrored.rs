@@ -0,0 +1,403 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use tokio::sync::{Notify, RwLock};
+use tracing::warn;
+
+use crate::{
+    config, error, noalbs,
+    state::{ClientStatus, ConnectionRole, ConnectionStatus, State, SwitcherState},
+};
+
+use super::{stats::SoftwareStats, BroadcastingSoftwareLogic};
+
+/// Time between syncs when none of the connections notified
+const SYNC_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Max time to wait for a mirror to switch scenes
+const MIRROR_SWITCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The main broadcasting software with its mirrors. Every connection keeps
+/// its own state, the state of the user follows the active connection which
+/// is the primary while it's connected, otherwise the first connected
+/// mirror. Scene switches go to every connected software, the other
+/// requests only go to the active one
+pub struct Mirrored {
+    members: Arc<Vec<Member>>,
+    sync_join: tokio::task::JoinHandle<()>,
+}
+
+struct Member {
+    name: String,
+    role: ConnectionRole,
+    connection: Box<dyn BroadcastingSoftwareLogic>,
+    state: noalbs::UserState,
+}
+
+impl Mirrored {
+    pub fn new(user: &State, state: noalbs::UserState) -> Self {
+        let config = &user.config;
+        let primary = (
+            "primary".to_string(),
+            ConnectionRole::Primary,
+            &config.software,
+        );
+        let mirrors = config
+            .mirrors
+            .iter()
+            .map(|m| (m.name.to_owned(), ConnectionRole::Mirror, &m.connection));
+
+        let members: Vec<Member> = std::iter::once(primary)
+            .chain(mirrors)
+            .map(|(name, role, software)| {
+                let member_state = member_state(user, software);
+
+                Member {
+                    name,
+                    role,
+                    connection: super::connect(software, member_state.clone()),
+                    state: member_state,
+                }
+            })
+            .collect();
+
+        let members = Arc::new(members);
+        let sync_join = tokio::spawn(Self::sync_loop(members.clone(), state));
+
+        Self { members, sync_join }
+    }
+
+    /// Keeps the state of the user in sync with the active connection
+    async fn sync_loop(members: Arc<Vec<Member>>, state: noalbs::UserState) {
+        let mut notifiers: Vec<Arc<Notify>> = Vec::new();
+
+        for member in members.iter() {
+            let bs = &member.state.read().await.broadcasting_software;
+            notifiers.push(bs.connected_notifier());
+            notifiers.push(bs.start_streaming_notifier());
+            notifiers.push(bs.switch_scene_notifier());
+        }
+
+        loop {
+            // Created before syncing so no notification gets missed
+            let notified = notifiers.iter().map(|n| Box::pin(n.notified()));
+            let notified = futures_util::future::select_all(notified);
+
+            sync(&members, &state).await;
+
+            tokio::select! {
+                _ = notified => {}
+                _ = tokio::time::sleep(SYNC_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn active(&self) -> Result<&Member, error::Error> {
+        let statuses = statuses(&self.members).await;
+
+        active_index(&statuses)
+            .map(|index| &self.members[index])
+            .ok_or(error::Error::UnableInitialConnection)
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for Mirrored {
+    async fn switch_scene(&self, scene: &str) -> Result<String, error::Error> {
+        let active = self.active().await?;
+        let switched = active.connection.switch_scene(scene).await?;
+
+        let mut mirrors = Vec::new();
+
+        for member in self.members.iter() {
+            if std::ptr::eq(member, active) {
+                continue;
+            }
+
+            let status = member.state.read().await.broadcasting_software.status;
+
+            if status == ClientStatus::Connected {
+                mirrors.push(member);
+            }
+        }
+
+        // A slow mirror shouldn't hold back the others or the switcher
+        let switches = mirrors.into_iter().map(|member| async move {
+            let switch = member.connection.switch_scene(scene);
+
+            match tokio::time::timeout(MIRROR_SWITCH_TIMEOUT, switch).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("Error mirroring the switch to {}: {}", member.name, e),
+                Err(_) => warn!("Timed out mirroring the switch to {}", member.name),
+            }
+        });
+
+        futures_util::future::join_all(switches).await;
+
+        Ok(switched)
+    }
+
+    async fn start_streaming(&self) -> Result<(), error::Error> {
+        self.active().await?.connection.start_streaming().await
+    }
+
+    async fn stop_streaming(&self) -> Result<(), error::Error> {
+        self.active().await?.connection.stop_streaming().await
+    }
+
+    async fn fix(&self) -> Result<(), error::Error> {
+        self.active().await?.connection.fix().await
+    }
+
+    async fn toggle_recording(&self) -> Result<(), error::Error> {
+        self.active().await?.connection.toggle_recording().await
+    }
+
+    async fn is_recording(&self) -> Result<bool, error::Error> {
+        self.active().await?.connection.is_recording().await
+    }
+
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        self.active().await?.connection.stats().await
+    }
+}
+
+impl Drop for Mirrored {
+    // Abort the spawned task
+    fn drop(&mut self) {
+        self.sync_join.abort();
+    }
+}
+
+/// State for a single connection with only the parts of the user state the
+/// connections read, which are the scenes. `sync` keeps them up to date
+fn member_state(user: &State, software: &config::SoftwareConnection) -> noalbs::UserState {
+    let switcher = config::Switcher {
+        switching_scenes: user.config.switcher.switching_scenes().to_owned(),
+        ..Default::default()
+    };
+
+    let config = config::Config {
+        user: user.config.user.to_owned(),
+        switcher,
+        software: software.to_owned(),
+        mirrors: Vec::new(),
+        chat: None,
+        optional_scenes: Default::default(),
+        optional_options: Default::default(),
+    };
+
+    let mut switcher_state = SwitcherState::default();
+    switcher_state.switchable_scenes = user.switcher_state.switchable_scenes.to_owned();
+
+    let state = State {
+        config,
+        switcher_state,
+        broadcasting_software: Default::default(),
+        event_senders: Vec::new(),
+    };
+
+    Arc::new(RwLock::new(state))
+}
+
+async fn statuses(members: &[Member]) -> Vec<ConnectionStatus> {
+    let mut statuses = Vec::with_capacity(members.len());
+
+    for member in members {
+        let bs = &member.state.read().await.broadcasting_software;
+
+        statuses.push(ConnectionStatus {
+            name: member.name.to_owned(),
+            role: member.role,
+            status: bs.status,
+            current_scene: bs.current_scene.to_owned(),
+            is_streaming: bs.is_streaming,
+            active: false,
+        });
+    }
+
+    statuses
+}
+
+/// The primary when it's connected, otherwise the first connected mirror
+fn active_index(statuses: &[ConnectionStatus]) -> Option<usize> {
+    let connected = |s: &&ConnectionStatus| s.status == ClientStatus::Connected;
+
+    statuses
+        .iter()
+        .position(|s| connected(&s) && s.role == ConnectionRole::Primary)
+        .or_else(|| statuses.iter().position(|s| connected(&s)))
+}
+
+/// Copies the state of the active connection to the state of the user like
+/// the events of a single connection would
+async fn sync(members: &[Member], state: &noalbs::UserState) {
+    // The scenes change with profiles, the schedule and new stream servers
+    let (switching_scenes, switchable) = {
+        let user = state.read().await;
+
+        (
+            user.config.switcher.switching_scenes().to_owned(),
+            user.switcher_state.switchable_scenes.to_owned(),
+        )
+    };
+
+    // The connections only notify the switches to switchable scenes
+    for member in members {
+        let mut member_state = member.state.write().await;
+        member_state.config.switcher.switching_scenes = switching_scenes.to_owned();
+        member_state.switcher_state.switchable_scenes = switchable.to_owned();
+    }
+
+    let mut statuses = statuses(members).await;
+    let active = active_index(&statuses);
+
+    let started_at = match active {
+        Some(index) => Some(
+            members[index]
+                .state
+                .read()
+                .await
+                .broadcasting_software
+                .last_stream_started_at,
+        ),
+        None => None,
+    };
+
+    // Everything got collected, the user state is only locked to update it
+    let mut user = state.write().await;
+    let bs = &mut user.broadcasting_software;
+
+    match active {
+        Some(index) => {
+            statuses[index].active = true;
+            let active = &statuses[index];
+
+            if bs.status != ClientStatus::Connected {
+                bs.status = ClientStatus::Connected;
+                bs.connected_notifier().notify_waiters();
+            }
+
+            if bs.current_scene != active.current_scene {
+                bs.current_scene = active.current_scene.to_owned();
+
+                if switchable.contains(&bs.current_scene) {
+                    bs.switch_scene_notifier().notify_waiters();
+                }
+            }
+
+            if !bs.is_streaming && active.is_streaming {
+                bs.start_streaming_notifier().notify_waiters();
+            }
+
+            bs.is_streaming = active.is_streaming;
+
+            if let Some(started_at) = started_at {
+                bs.last_stream_started_at = started_at;
+            }
+        }
+        None => {
+            bs.status = ClientStatus::Disconnected;
+            bs.is_streaming = false;
+        }
+    }
+
+    bs.connections = statuses;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::broadcasting_software::fake_obs::{user_state, FakeObs};
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn mirrored_config(primary: &FakeObs, mirror: &FakeObs) -> config::Config {
+        serde_json::from_value(json!({
+            "user": { "name": "test" },
+            "switcher": {},
+            "software": { "type": "Obs", "host": "127.0.0.1", "port": primary.config().port },
+            "mirrors": [
+                { "name": "backup", "type": "ObsV5", "host": "127.0.0.1", "port": mirror.config().port }
+            ],
+            "optionalScenes": {},
+            "optionalOptions": {}
+        }))
+        .unwrap()
+    }
+
+    async fn wait_for<F>(state: &noalbs::UserState, check: F)
+    where
+        F: Fn(&State) -> bool,
+    {
+        let deadline = tokio::time::Instant::now() + WAIT;
+
+        while !check(&*state.read().await) {
+            assert!(tokio::time::Instant::now() < deadline, "timed out");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    fn connected(state: &State) -> Vec<ClientStatus> {
+        let connections = &state.broadcasting_software.connections;
+        connections.iter().map(|c| c.status).collect()
+    }
+
+    #[tokio::test]
+    async fn mirrors_switches_and_fails_over() {
+        let primary = FakeObs::start(&["intro", "live", "low"]).await;
+        let mirror = FakeObs::start_v5(&["intro", "live", "low"], None).await;
+
+        let state = user_state();
+        let mut user = State {
+            config: mirrored_config(&primary, &mirror),
+            switcher_state: Default::default(),
+            broadcasting_software: Default::default(),
+            event_senders: Vec::new(),
+        };
+        user.set_all_switchable_scenes();
+
+        let mirrored = Mirrored::new(&user, state.clone());
+
+        let both = vec![ClientStatus::Connected, ClientStatus::Connected];
+        wait_for(&state, |s| connected(s) == both).await;
+
+        assert_eq!(
+            ClientStatus::Connected,
+            state.read().await.broadcasting_software.status
+        );
+
+        assert_eq!("live", mirrored.switch_scene("live").await.unwrap());
+        assert_eq!("live", primary.current_scene());
+        assert_eq!("live", mirror.current_scene());
+
+        wait_for(&state, |s| s.broadcasting_software.current_scene == "live").await;
+
+        // The primary goes away, the mirror takes over
+        primary.set_accepting(false);
+        primary.disconnect();
+
+        wait_for(&state, |s| {
+            connected(s) == vec![ClientStatus::Disconnected, ClientStatus::Connected]
+        })
+        .await;
+
+        let connections = state.read().await.broadcasting_software.connections.clone();
+        assert!(!connections[0].active);
+        assert!(connections[1].active);
+        assert_eq!("backup", connections[1].name);
+
+        assert_eq!(
+            ClientStatus::Connected,
+            state.read().await.broadcasting_software.status
+        );
+
+        mirror.switch_scene("low");
+        wait_for(&state, |s| s.broadcasting_software.current_scene == "low").await;
+
+        assert_eq!("intro", mirrored.switch_scene("intro").await.unwrap());
+        assert_eq!("intro", mirror.current_scene());
+    }
+}
@@ -1,8 +1,19 @@
 use async_trait::async_trait;
 
-use crate::error::Error;
+use crate::{config, error::Error, noalbs};
 
+#[cfg(test)]
+pub mod fake_obs;
+#[cfg(test)]
+pub mod fake_vmix;
+pub mod mirrored;
 pub mod obs;
+pub mod obs_v5;
+pub mod pipeline;
+pub mod replay;
+pub mod slobs;
+pub mod stats;
+pub mod vmix;
 
 #[async_trait]
 pub trait BroadcastingSoftwareLogic: Send + Sync {
@@ -17,4 +28,32 @@ pub trait BroadcastingSoftwareLogic: Send + Sync {
     async fn is_recording(&self) -> Result<bool, Error>;
 
     async fn fix(&self) -> Result<(), Error>;
+
+    /// Telemetry like the output bitrate, dropped frames and CPU usage
+    async fn stats(&self) -> Result<stats::SoftwareStats, Error>;
+}
+
+/// Connects to the broadcasting software, the connection keeps the state
+/// of the broadcasting software up to date
+pub fn connect(
+    software: &config::SoftwareConnection,
+    state: noalbs::UserState,
+) -> Box<dyn BroadcastingSoftwareLogic> {
+    match software {
+        config::SoftwareConnection::Obs(obs_conf) => {
+            Box::new(obs::Obs::new(obs_conf.clone(), state))
+        }
+        config::SoftwareConnection::ObsV5(obs_conf) => {
+            Box::new(obs_v5::ObsV5::new(obs_conf.clone(), state))
+        }
+        config::SoftwareConnection::Slobs(slobs_conf) => {
+            Box::new(slobs::Slobs::new(slobs_conf.clone(), state))
+        }
+        config::SoftwareConnection::Vmix(vmix_conf) => {
+            Box::new(vmix::Vmix::new(vmix_conf.clone(), state))
+        }
+        config::SoftwareConnection::Pipeline(pipeline_conf) => {
+            Box::new(pipeline::Pipeline::new(pipeline_conf.clone(), state))
+        }
+    }
 }
@@ -9,12 +9,19 @@ use tracing::{error, info, warn};
 
 use crate::{config, error, noalbs, state::ClientStatus};
 
-use super::BroadcastingSoftwareLogic;
+use super::{
+    stats::{Frames, SoftwareStats},
+    BroadcastingSoftwareLogic,
+};
 
 pub struct Obs {
     connection: Arc<Mutex<Option<obws::Client>>>,
     connection_join: tokio::task::JoinHandle<()>,
     event_join: tokio::task::JoinHandle<()>,
+
+    /// Bitrate and dropped frames of the last StreamStatus event, OBS only
+    /// reports those in that event
+    stream_status: Arc<std::sync::Mutex<Option<(u64, Frames)>>>,
 }
 
 impl Obs {
@@ -39,18 +46,21 @@ impl Obs {
             connection.run().await;
         });
 
-        let event_join = tokio::spawn(Self::event_handler(event_rx, state));
+        let stream_status = Arc::new(std::sync::Mutex::new(None));
+        let event_join = tokio::spawn(Self::event_handler(event_rx, state, stream_status.clone()));
 
         Self {
             connection,
             connection_join,
             event_join,
+            stream_status,
         }
     }
 
     async fn event_handler(
         mut events: mpsc::Receiver<obws::events::Event>,
         state: noalbs::UserState,
+        stream_status: Arc<std::sync::Mutex<Option<(u64, Frames)>>>,
     ) {
         while let Some(event) = events.recv().await {
             match event.ty {
@@ -79,9 +89,15 @@ impl Obs {
                         .notify_waiters();
                 }
                 EventType::StreamStopped => {
+                    *stream_status.lock().unwrap() = None;
+
                     let mut l = state.write().await;
                     l.broadcasting_software.is_streaming = false;
                 }
+                EventType::StreamStatus(status) => {
+                    let dropped = Frames::new(status.num_dropped_frames, status.num_total_frames);
+                    *stream_status.lock().unwrap() = Some((status.kbits_per_sec, dropped));
+                }
                 _ => continue,
             }
         }
@@ -240,6 +256,43 @@ impl BroadcastingSoftwareLogic for Obs {
         let status = client.recording().get_recording_status().await?;
         Ok(status.is_recording)
     }
+
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        let connection = self.connection.lock().await;
+
+        let client = match &*connection {
+            Some(client) => client,
+            None => return Err(error::Error::UnableInitialConnection),
+        };
+
+        let stats = client.general().get_stats().await?;
+        let status = client.streaming().get_streaming_status().await?;
+
+        let mut software_stats = SoftwareStats {
+            streaming_duration: status.stream_timecode.map(|t| t.num_seconds() as u64),
+            recording_duration: status.rec_timecode.map(|t| t.num_seconds() as u64),
+            skipped_frames: Some(Frames::new(
+                stats.output_skipped_frames,
+                stats.output_total_frames,
+            )),
+            lagged_frames: Some(Frames::new(
+                stats.render_missed_frames,
+                stats.render_total_frames,
+            )),
+            cpu_usage: Some(stats.cpu_usage),
+            fps: Some(stats.fps),
+            ..Default::default()
+        };
+
+        if status.streaming {
+            if let Some((bitrate, dropped)) = *self.stream_status.lock().unwrap() {
+                software_stats.bitrate = Some(bitrate);
+                software_stats.dropped_frames = Some(dropped);
+            }
+        }
+
+        Ok(software_stats)
+    }
 }
 
 /// The real connection to OBS, automatically keeps trying to connect.
@@ -389,3 +442,141 @@ struct SlideshowFile {
     /// Location of the file to display.
     pub value: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcasting_software::fake_obs::{user_state, FakeObs};
+    use serde_json::json;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    async fn connect(fake: &FakeObs, state: &noalbs::UserState) -> Obs {
+        let connected = state
+            .read()
+            .await
+            .broadcasting_software
+            .connected_notifier();
+        let notified = connected.notified();
+
+        let obs = Obs::new(fake.config(), state.clone());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        obs
+    }
+
+    #[tokio::test]
+    async fn follows_scenes() {
+        let fake = FakeObs::start(&["intro", "live", "low", "offline"]).await;
+        let state = user_state();
+        let obs = connect(&fake, &state).await;
+
+        assert_eq!(
+            "intro",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        // Waiting for a switchable scene
+        let switched = state
+            .read()
+            .await
+            .broadcasting_software
+            .switch_scene_notifier();
+        let notified = switched.notified();
+
+        fake.switch_scene("live");
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            "live",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        assert_eq!("low", obs.switch_scene("low").await.unwrap());
+        assert_eq!("low", fake.current_scene());
+    }
+
+    #[tokio::test]
+    async fn reconnects() {
+        let fake = FakeObs::start(&["live", "low", "offline"]).await;
+        let state = user_state();
+        let _obs = connect(&fake, &state).await;
+
+        let started = state
+            .read()
+            .await
+            .broadcasting_software
+            .start_streaming_notifier();
+        let notified = started.notified();
+
+        fake.start_streaming();
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        let connected = state
+            .read()
+            .await
+            .broadcasting_software
+            .connected_notifier();
+        let notified = connected.notified();
+
+        let refused = fake.refused_notifier();
+        let reconnecting = refused.notified();
+
+        // OBS closes and isn't reachable for a bit, the connection is marked
+        // as disconnected before it tries to reconnect
+        fake.set_accepting(false);
+        fake.disconnect();
+        tokio::time::timeout(WAIT, reconnecting).await.unwrap();
+
+        {
+            let bs = &state.read().await.broadcasting_software;
+            assert_eq!(ClientStatus::Disconnected, bs.status);
+            assert!(!bs.is_streaming);
+        }
+
+        // The first retry waits 2 seconds
+        fake.set_accepting(true);
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(2, fake.connections());
+
+        let bs = &state.read().await.broadcasting_software;
+        assert_eq!(ClientStatus::Connected, bs.status);
+        assert!(bs.is_streaming);
+    }
+
+    #[tokio::test]
+    async fn fix_restarts_stream_sources() {
+        let fake = FakeObs::start(&["live"]).await;
+        let state = user_state();
+        let obs = connect(&fake, &state).await;
+
+        fake.add_media_source(
+            "srt",
+            "ffmpeg_source",
+            json!({ "input": "SRT://127.0.0.1:9000" }),
+        );
+        fake.add_media_source(
+            "clip",
+            "ffmpeg_source",
+            json!({ "input": "/clips/intro.mp4" }),
+        );
+        fake.add_media_source(
+            "playlist",
+            "vlc_source",
+            json!({ "playlist": [{ "value": "rtmp://127.0.0.1/publish/live" }] }),
+        );
+
+        obs.fix().await.unwrap();
+
+        let sources = |request_type: &str| {
+            fake.received(request_type)
+                .iter()
+                .map(|r| r["sourceName"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(vec!["srt", "playlist"], sources("StopMedia"));
+        assert_eq!(vec!["srt", "playlist"], sources("PlayPauseMedia"));
+    }
+}
@@ -0,0 +1,892 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, oneshot, Mutex},
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{error, info, warn};
+
+use crate::{config, error, noalbs, state::ClientStatus};
+
+use super::{
+    stats::{Frames, SoftwareStats},
+    BroadcastingSoftwareLogic,
+};
+
+const RPC_VERSION: u8 = 1;
+
+/// Events of the General, Scenes and Outputs categories
+const EVENT_SUBSCRIPTIONS: u32 = (1 << 0) | (1 << 2) | (1 << 6);
+
+/// OBS that accepts the connection but never identifies shouldn't block
+/// reconnecting forever
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connection to OBS 28 and newer which only speak the obs-websocket 5.x
+/// protocol
+pub struct ObsV5 {
+    connection: Arc<Mutex<Option<Client>>>,
+    connection_join: tokio::task::JoinHandle<()>,
+    event_join: tokio::task::JoinHandle<()>,
+
+    /// Sent bytes of the previous stats request, used for the bitrate
+    last_output_bytes: std::sync::Mutex<Option<(u64, Instant)>>,
+}
+
+impl ObsV5 {
+    pub fn new(connection_info: config::ObsConfig, state: noalbs::UserState) -> Self {
+        // OBS connection will be held in this arc mutex
+        let connection = Arc::new(Mutex::new(None));
+
+        // Will be used to receive events from OBS
+        let (event_tx, event_rx) = mpsc::channel(100);
+
+        let connection_inner = connection.clone();
+        let state_inner = state.clone();
+        let connection_join = tokio::spawn(async move {
+            let connection = InnerConnection {
+                connection_info,
+                state: state_inner,
+                connection: connection_inner,
+                event_sender: event_tx,
+            };
+
+            connection.run().await;
+        });
+
+        let event_join = tokio::spawn(Self::event_handler(event_rx, state));
+
+        Self {
+            connection,
+            connection_join,
+            event_join,
+            last_output_bytes: std::sync::Mutex::new(None),
+        }
+    }
+
+    async fn event_handler(mut events: mpsc::Receiver<Event>, state: noalbs::UserState) {
+        while let Some(event) = events.recv().await {
+            match event.event_type.as_str() {
+                "CurrentProgramSceneChanged" => {
+                    let scene_name = match event.event_data["sceneName"].as_str() {
+                        Some(name) => name.to_owned(),
+                        None => continue,
+                    };
+
+                    let mut l = state.write().await;
+
+                    let switchable = &l.switcher_state.switchable_scenes;
+                    if switchable.contains(&scene_name) {
+                        l.broadcasting_software
+                            .switch_scene_notifier()
+                            .notify_waiters();
+                    }
+
+                    l.broadcasting_software.current_scene = scene_name;
+                }
+                "StreamStateChanged" => match event.event_data["outputState"].as_str() {
+                    Some("OBS_WEBSOCKET_OUTPUT_STARTED") => {
+                        let mut l = state.write().await;
+                        l.broadcasting_software.is_streaming = true;
+                        l.broadcasting_software.last_stream_started_at = std::time::Instant::now();
+
+                        l.broadcasting_software
+                            .start_streaming_notifier()
+                            .notify_waiters();
+                    }
+                    Some("OBS_WEBSOCKET_OUTPUT_STOPPED") => {
+                        let mut l = state.write().await;
+                        l.broadcasting_software.is_streaming = false;
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            }
+        }
+    }
+
+    async fn request<T>(&self, request_type: &str, data: Value) -> Result<T, error::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let connection = self.connection.lock().await;
+
+        let client = match &*connection {
+            Some(client) => client,
+            None => return Err(error::Error::UnableInitialConnection),
+        };
+
+        client.request(request_type, data).await
+    }
+
+    async fn get_scenes(&self) -> Result<Vec<String>, error::Error> {
+        let scenes: SceneList = self.request("GetSceneList", json!({})).await?;
+
+        Ok(scenes.scenes.into_iter().map(|s| s.scene_name).collect())
+    }
+
+    /// Bitrate in Kbps since the previous request, the average of the
+    /// whole stream when there is no previous request
+    fn bitrate(&self, stream: &StreamStatus) -> u64 {
+        let now = Instant::now();
+        let mut last = self.last_output_bytes.lock().unwrap();
+
+        let bitrate = match *last {
+            Some((bytes, at)) if stream.output_bytes >= bytes => {
+                let millis = now.duration_since(at).as_millis() as u64;
+                (stream.output_bytes - bytes) * 8 / millis.max(1)
+            }
+            _ if stream.output_duration > 0 => stream.output_bytes * 8 / stream.output_duration,
+            _ => 0,
+        };
+
+        *last = Some((stream.output_bytes, now));
+
+        bitrate
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for ObsV5 {
+    async fn switch_scene(&self, scene: &str) -> Result<String, error::Error> {
+        let scenes = self.get_scenes().await?;
+
+        let fuse = fuse_rust::Fuse::default();
+        let res = fuse.search_text_in_iterable(scene, scenes.iter());
+
+        let scene = if !res.is_empty() {
+            &scenes[res[0].index]
+        } else {
+            scene
+        };
+
+        self.request::<Value>("SetCurrentProgramScene", json!({ "sceneName": scene }))
+            .await?;
+
+        Ok(scene.to_owned())
+    }
+
+    async fn start_streaming(&self) -> Result<(), error::Error> {
+        self.request::<Value>("StartStream", json!({})).await?;
+        Ok(())
+    }
+
+    async fn stop_streaming(&self) -> Result<(), error::Error> {
+        self.request::<Value>("StopStream", json!({})).await?;
+        Ok(())
+    }
+
+    /// Restarts the playing media inputs that pull a RTMP or SRT stream
+    async fn fix(&self) -> Result<(), error::Error> {
+        let inputs: InputList = self.request("GetInputList", json!({})).await?;
+
+        for input in inputs.inputs {
+            let kind = input.unversioned_input_kind.as_deref().unwrap_or_default();
+
+            if kind != "ffmpeg_source" && kind != "vlc_source" {
+                continue;
+            }
+
+            let name = json!({ "inputName": input.input_name });
+
+            let status: MediaInputStatus =
+                self.request("GetMediaInputStatus", name.clone()).await?;
+
+            if status.media_state != "OBS_MEDIA_STATE_PLAYING" {
+                continue;
+            }
+
+            let settings: InputSettings = self.request("GetInputSettings", name).await?;
+            let settings = settings.input_settings;
+
+            let media_inputs = match kind {
+                "ffmpeg_source" => settings["input"]
+                    .as_str()
+                    .map(|input| vec![input.to_lowercase()])
+                    .unwrap_or_default(),
+                _ => settings["playlist"]
+                    .as_array()
+                    .map(|playlist| {
+                        playlist
+                            .iter()
+                            .filter_map(|file| file["value"].as_str())
+                            .map(str::to_lowercase)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            };
+
+            if !media_inputs
+                .iter()
+                .any(|m| m.starts_with("rtmp") || m.starts_with("srt"))
+            {
+                continue;
+            }
+
+            self.request::<Value>(
+                "TriggerMediaInputAction",
+                json!({
+                    "inputName": input.input_name,
+                    "mediaAction": "OBS_WEBSOCKET_MEDIA_INPUT_ACTION_RESTART"
+                }),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn toggle_recording(&self) -> Result<(), error::Error> {
+        self.request::<Value>("ToggleRecord", json!({})).await?;
+        Ok(())
+    }
+
+    async fn is_recording(&self) -> Result<bool, error::Error> {
+        let status: OutputStatus = self.request("GetRecordStatus", json!({})).await?;
+        Ok(status.output_active)
+    }
+
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        let stats: Stats = self.request("GetStats", json!({})).await?;
+        let stream: StreamStatus = self.request("GetStreamStatus", json!({})).await?;
+        let record: OutputStatus = self.request("GetRecordStatus", json!({})).await?;
+
+        let mut software_stats = SoftwareStats {
+            skipped_frames: Some(Frames::new(
+                stats.output_skipped_frames,
+                stats.output_total_frames,
+            )),
+            lagged_frames: Some(Frames::new(
+                stats.render_skipped_frames,
+                stats.render_total_frames,
+            )),
+            cpu_usage: Some(stats.cpu_usage),
+            fps: Some(stats.active_fps),
+            ..Default::default()
+        };
+
+        if stream.output_active {
+            software_stats.streaming_duration = Some(stream.output_duration / 1000);
+            software_stats.bitrate = Some(self.bitrate(&stream));
+            software_stats.dropped_frames = Some(Frames::new(
+                stream.output_skipped_frames,
+                stream.output_total_frames,
+            ));
+        } else {
+            *self.last_output_bytes.lock().unwrap() = None;
+        }
+
+        if record.output_active {
+            software_stats.recording_duration = Some(record.output_duration / 1000);
+        }
+
+        Ok(software_stats)
+    }
+}
+
+impl Drop for ObsV5 {
+    // Abort the spawned tasks
+    fn drop(&mut self) {
+        self.connection_join.abort();
+        self.event_join.abort();
+    }
+}
+
+/// The real connection to OBS, automatically keeps trying to connect.
+struct InnerConnection {
+    connection_info: config::ObsConfig,
+    state: noalbs::UserState,
+    connection: Arc<Mutex<Option<Client>>>,
+    event_sender: mpsc::Sender<Event>,
+}
+
+impl InnerConnection {
+    async fn run(&self) {
+        loop {
+            let (client, mut events) = self.get_client().await;
+
+            if let Err(e) = self.set_connected(&client).await {
+                error!("Error getting the OBS status: {}", e);
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                continue;
+            }
+
+            {
+                let mut connection = self.connection.lock().await;
+                *connection = Some(client);
+            }
+
+            while let Some(event) = events.recv().await {
+                let _ = self.event_sender.send(event).await;
+            }
+
+            warn!("Disconnected");
+
+            {
+                let state = &mut self.state.write().await;
+                let bs = &mut state.broadcasting_software;
+                bs.status = ClientStatus::Disconnected;
+                bs.is_streaming = false;
+            }
+        }
+    }
+
+    async fn set_connected(&self, client: &Client) -> Result<(), error::Error> {
+        let scenes: SceneList = client.request("GetSceneList", json!({})).await?;
+        let stream: OutputStatus = client.request("GetStreamStatus", json!({})).await?;
+
+        let state = &mut self.state.write().await;
+        let bs = &mut state.broadcasting_software;
+
+        bs.current_scene = scenes.current_program_scene_name;
+        bs.is_streaming = stream.output_active;
+        bs.status = ClientStatus::Connected;
+
+        let bs = &state.broadcasting_software;
+        bs.connected_notifier().notify_waiters();
+
+        if bs.is_streaming {
+            bs.start_streaming_notifier().notify_waiters();
+        }
+
+        if state
+            .switcher_state
+            .switchable_scenes
+            .contains(&bs.current_scene)
+        {
+            bs.switch_scene_notifier().notify_waiters();
+        }
+
+        Ok(())
+    }
+
+    /// Attempts to connect to OBS
+    ///
+    /// Blocks until a successful connection has been established.
+    /// An exponential backoff strategy is used to keep retrying to connect.
+    /// This will grow until the 5th retry failure after which the max seconds
+    /// will be reached of 32 seconds.
+    async fn get_client(&self) -> (Client, mpsc::UnboundedReceiver<Event>) {
+        let mut retry_grow = 1;
+
+        loop {
+            info!("Connecting");
+            let info = &self.connection_info;
+
+            match Client::connect(&info.host, info.port, info.password.as_deref()).await {
+                Ok(connected) => {
+                    info!("Connected");
+                    break connected;
+                }
+                Err(error::Error::ObsAuthFailed) => {
+                    error!("Can't authenticate");
+                    info!("trying to connect again in {} seconds", 10);
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    continue;
+                }
+                Err(e) => error!("Error while trying to connect to OBS: {}", e),
+            };
+
+            let wait = 1 << retry_grow;
+            warn!("Unable to connect");
+            info!("trying to connect again in {} seconds", wait);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+
+            if retry_grow < 5 {
+                retry_grow += 1;
+            }
+        }
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type PendingRequests = Arc<std::sync::Mutex<HashMap<String, oneshot::Sender<RequestResponse>>>>;
+
+/// An identified obs-websocket 5.x session
+struct Client {
+    sink: Mutex<SplitSink<Socket, Message>>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    read_join: tokio::task::JoinHandle<()>,
+}
+
+impl Client {
+    /// Connects and identifies, the receiver gets the events till the
+    /// connection closes
+    async fn connect(
+        host: &str,
+        port: u16,
+        password: Option<&str>,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Event>), error::Error> {
+        match tokio::time::timeout(HANDSHAKE_TIMEOUT, Self::handshake(host, port, password)).await {
+            Ok(connected) => connected,
+            Err(_) => Err(error::Error::ObsRequest("Handshake timed out".to_string())),
+        }
+    }
+
+    async fn handshake(
+        host: &str,
+        port: u16,
+        password: Option<&str>,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Event>), error::Error> {
+        let url = format!("ws://{}:{}", host, port);
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let (mut sink, mut stream) = socket.split();
+
+        let hello: Hello = match next_frame(&mut stream).await? {
+            Frame { op: 0, d } => serde_json::from_value(d)?,
+            _ => return Err(error::Error::ObsRequest("Expected Hello".to_string())),
+        };
+
+        let authentication = match (hello.authentication, password) {
+            (Some(auth), Some(password)) => {
+                Some(auth_string(password, &auth.salt, &auth.challenge))
+            }
+            (Some(_), None) => return Err(error::Error::ObsAuthFailed),
+            (None, _) => None,
+        };
+
+        let identify = json!({
+            "op": 1,
+            "d": {
+                "rpcVersion": RPC_VERSION,
+                "authentication": authentication,
+                "eventSubscriptions": EVENT_SUBSCRIPTIONS
+            }
+        });
+
+        sink.send(Message::Text(identify.to_string())).await?;
+
+        // OBS closes the connection when the authentication is wrong
+        match next_frame(&mut stream).await {
+            Ok(Frame { op: 2, .. }) => {}
+            Ok(_) => return Err(error::Error::ObsRequest("Expected Identified".to_string())),
+            Err(_) => return Err(error::Error::ObsAuthFailed),
+        }
+
+        let pending = PendingRequests::default();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+
+        let read_join = tokio::spawn(Self::read_loop(stream, pending.clone(), event_tx));
+
+        let client = Self {
+            sink: Mutex::new(sink),
+            pending,
+            next_id: AtomicU64::new(0),
+            read_join,
+        };
+
+        Ok((client, event_rx))
+    }
+
+    /// Sends responses to the requests waiting for them and events to the
+    /// receiver. Ends when the connection closes
+    async fn read_loop(
+        mut stream: SplitStream<Socket>,
+        pending: PendingRequests,
+        events: mpsc::UnboundedSender<Event>,
+    ) {
+        while let Ok(frame) = next_frame(&mut stream).await {
+            match frame.op {
+                5 => {
+                    if let Ok(event) = serde_json::from_value(frame.d) {
+                        let _ = events.send(event);
+                    }
+                }
+                7 => {
+                    let response: RequestResponse = match serde_json::from_value(frame.d) {
+                        Ok(response) => response,
+                        Err(_) => continue,
+                    };
+
+                    let waiting = pending.lock().unwrap().remove(&response.request_id);
+
+                    if let Some(waiting) = waiting {
+                        let _ = waiting.send(response);
+                    }
+                }
+                _ => continue,
+            }
+        }
+
+        // Fails the requests that are still waiting
+        pending.lock().unwrap().clear();
+    }
+
+    async fn request<T>(&self, request_type: &str, data: Value) -> Result<T, error::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let request_id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let (tx, rx) = oneshot::channel();
+
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(request_id.to_owned(), tx);
+
+        let request = json!({
+            "op": 6,
+            "d": {
+                "requestType": request_type,
+                "requestId": request_id,
+                "requestData": data
+            }
+        });
+
+        if let Err(e) = self
+            .sink
+            .lock()
+            .await
+            .send(Message::Text(request.to_string()))
+            .await
+        {
+            self.pending.lock().unwrap().remove(&request_id);
+            return Err(e.into());
+        }
+
+        let response = rx
+            .await
+            .map_err(|_| error::Error::ObsRequest("Connection closed".to_string()))?;
+
+        let status = response.request_status;
+
+        if !status.result {
+            return Err(error::Error::ObsRequest(format!(
+                "{} failed ({}): {}",
+                request_type,
+                status.code,
+                status.comment.unwrap_or_default()
+            )));
+        }
+
+        Ok(serde_json::from_value(
+            response.response_data.unwrap_or(Value::Null),
+        )?)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.read_join.abort();
+    }
+}
+
+async fn next_frame(stream: &mut SplitStream<Socket>) -> Result<Frame, error::Error> {
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+            Message::Close(_) => break,
+            _ => continue,
+        }
+    }
+
+    Err(error::Error::ObsRequest("Connection closed".to_string()))
+}
+
+/// The authentication string obs-websocket expects when a password is set
+pub fn auth_string(password: &str, salt: &str, challenge: &str) -> String {
+    let secret = base64::encode(Sha256::digest(format!("{}{}", password, salt)));
+
+    base64::encode(Sha256::digest(format!("{}{}", secret, challenge)))
+}
+
+#[derive(Deserialize)]
+struct Frame {
+    op: u8,
+    d: Value,
+}
+
+#[derive(Deserialize)]
+struct Hello {
+    authentication: Option<Authentication>,
+}
+
+#[derive(Deserialize)]
+struct Authentication {
+    challenge: String,
+    salt: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub event_type: String,
+
+    #[serde(default)]
+    pub event_data: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RequestResponse {
+    request_id: String,
+    request_status: RequestStatus,
+    response_data: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct RequestStatus {
+    result: bool,
+    code: u16,
+    comment: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SceneList {
+    current_program_scene_name: String,
+    scenes: Vec<Scene>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Scene {
+    scene_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OutputStatus {
+    output_active: bool,
+
+    /// Milliseconds since the output started
+    #[serde(default)]
+    output_duration: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamStatus {
+    output_active: bool,
+    output_duration: u64,
+    output_bytes: u64,
+
+    /// Frames dropped because of the network
+    output_skipped_frames: u64,
+    output_total_frames: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stats {
+    cpu_usage: f64,
+    active_fps: f64,
+    render_skipped_frames: u64,
+    render_total_frames: u64,
+    output_skipped_frames: u64,
+    output_total_frames: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputList {
+    inputs: Vec<Input>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Input {
+    input_name: String,
+    unversioned_input_kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InputSettings {
+    input_settings: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MediaInputStatus {
+    media_state: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcasting_software::fake_obs::{user_state, FakeObs};
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    async fn connect(connection_info: config::ObsConfig, state: &noalbs::UserState) -> ObsV5 {
+        let connected = state
+            .read()
+            .await
+            .broadcasting_software
+            .connected_notifier();
+        let notified = connected.notified();
+
+        let obs = ObsV5::new(connection_info, state.clone());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        obs
+    }
+
+    #[test]
+    fn authentication() {
+        // Example from the obs-websocket protocol docs
+        assert_eq!(
+            "1Ct943GAT+6YQUUX47Ia/ncufilbe6+oD6lY+5kaCu4=",
+            auth_string(
+                "supersecretpassword",
+                "lM1GncleQOaCu9lT1yeUZhFYnqhsLLP1G5lAGo3ixaI=",
+                "+IxH4CnCiqpX1rM9scsNynZzbOe4KhDeYcTNS3PDaeY="
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn follows_scenes() {
+        let fake = FakeObs::start_v5(&["intro", "live", "low"], Some("hunter2")).await;
+        let state = user_state();
+
+        let mut connection_info = fake.config();
+        connection_info.password = Some("hunter2".to_string());
+
+        let obs = connect(connection_info, &state).await;
+
+        assert_eq!(
+            "intro",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        let switched = state
+            .read()
+            .await
+            .broadcasting_software
+            .switch_scene_notifier();
+        let notified = switched.notified();
+
+        fake.switch_scene("live");
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            "live",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        assert_eq!("low", obs.switch_scene("low").await.unwrap());
+        assert_eq!("low", fake.current_scene());
+
+        let started = state
+            .read()
+            .await
+            .broadcasting_software
+            .start_streaming_notifier();
+        let notified = started.notified();
+
+        obs.start_streaming().await.unwrap();
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert!(state.read().await.broadcasting_software.is_streaming);
+
+        obs.toggle_recording().await.unwrap();
+        assert!(obs.is_recording().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn wrong_password() {
+        let fake = FakeObs::start_v5(&["live"], Some("hunter2")).await;
+        let state = user_state();
+
+        let mut connection_info = fake.config();
+        connection_info.password = Some("password".to_string());
+
+        let closed = fake.closed_notifier();
+        let notified = closed.notified();
+
+        let obs = ObsV5::new(connection_info, state.clone());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(1, fake.connections());
+        assert_eq!(
+            ClientStatus::Disconnected,
+            state.read().await.broadcasting_software.status
+        );
+        assert!(obs.switch_scene("live").await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn handshake_times_out() {
+        // Accepts the connection but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let result = Client::connect("127.0.0.1", port, None).await;
+
+        assert!(matches!(result, Err(error::Error::ObsRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn fix_restarts_stream_inputs() {
+        let fake = FakeObs::start_v5(&["live"], None).await;
+        let state = user_state();
+        let obs = connect(fake.config(), &state).await;
+
+        fake.add_media_source(
+            "srt",
+            "ffmpeg_source",
+            json!({ "input": "SRT://127.0.0.1:9000" }),
+        );
+        fake.add_media_source(
+            "clip",
+            "ffmpeg_source",
+            json!({ "input": "/clips/intro.mp4" }),
+        );
+        fake.add_media_source(
+            "playlist",
+            "vlc_source",
+            json!({ "playlist": [{ "value": "rtmp://127.0.0.1/publish/live" }] }),
+        );
+
+        obs.fix().await.unwrap();
+
+        let restarted = fake
+            .received("TriggerMediaInputAction")
+            .iter()
+            .map(|r| r["inputName"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec!["srt", "playlist"], restarted);
+    }
+
+    #[tokio::test]
+    async fn stats() {
+        let fake = FakeObs::start_v5(&["live"], None).await;
+        let state = user_state();
+        let obs = connect(fake.config(), &state).await;
+
+        let stats = obs.stats().await.unwrap();
+        assert_eq!(None, stats.streaming_duration);
+        assert_eq!(None, stats.bitrate);
+        assert_eq!(Some(12.5), stats.cpu_usage);
+
+        obs.start_streaming().await.unwrap();
+
+        let stats = obs.stats().await.unwrap();
+        assert_eq!(Some(60), stats.streaming_duration);
+        assert_eq!(None, stats.recording_duration);
+        assert_eq!(Some(6000), stats.bitrate);
+        assert_eq!(Some(Frames::new(12, 3600)), stats.dropped_frames);
+        assert_eq!(Some(Frames::new(0, 3600)), stats.lagged_frames);
+        assert_eq!(Some(60.0), stats.fps);
+    }
+}
@@ -0,0 +1,453 @@
+use std::{
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use tokio::{
+    process::{Child, Command},
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use tracing::{error, info, warn};
+
+use crate::{config::PipelineConfig, error, noalbs, state::ClientStatus};
+
+use super::{stats::SoftwareStats, BroadcastingSoftwareLogic};
+
+/// Time to wait before starting the process again after it exited
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
+/// Headless broadcasting software, supervises a local process like ffmpeg
+/// that forwards the input of the current scene to the output. Switching
+/// scenes restarts the process with the input of the new scene
+pub struct Pipeline {
+    scenes: Vec<String>,
+    requests: mpsc::Sender<(Request, oneshot::Sender<Result<(), error::Error>>)>,
+    started: Arc<Mutex<Option<Instant>>>,
+    supervisor_join: tokio::task::JoinHandle<()>,
+
+    /// Arguments of every process that got started
+    #[cfg(test)]
+    spawned: mpsc::UnboundedReceiver<Vec<String>>,
+}
+
+#[derive(Debug)]
+enum Request {
+    Switch(String),
+    Start,
+    Stop,
+    Restart,
+}
+
+impl Pipeline {
+    pub fn new(config: PipelineConfig, state: noalbs::UserState) -> Self {
+        let scenes = config.scenes.keys().cloned().collect();
+        let (requests, requests_rx) = mpsc::channel(8);
+        let started = Arc::new(Mutex::new(None));
+
+        #[cfg(test)]
+        let (spawned_tx, spawned) = mpsc::unbounded_channel();
+
+        let started_inner = started.clone();
+        let supervisor_join = tokio::spawn(async move {
+            let scene = state
+                .read()
+                .await
+                .config
+                .switcher
+                .switching_scenes()
+                .offline
+                .to_owned();
+
+            let supervisor = Supervisor {
+                config,
+                state,
+                scene,
+                streaming: false,
+                child: None,
+                started: started_inner,
+                restart_at: None,
+                #[cfg(test)]
+                spawned: spawned_tx,
+            };
+
+            supervisor.run(requests_rx).await;
+        });
+
+        Self {
+            scenes,
+            requests,
+            started,
+            supervisor_join,
+            #[cfg(test)]
+            spawned,
+        }
+    }
+
+    async fn request(&self, request: Request) -> Result<(), error::Error> {
+        let (tx, rx) = oneshot::channel();
+        let stopped = || error::Error::Pipeline("Supervisor stopped".to_string());
+
+        self.requests
+            .send((request, tx))
+            .await
+            .map_err(|_| stopped())?;
+
+        rx.await.map_err(|_| stopped())?
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for Pipeline {
+    async fn switch_scene(&self, scene: &str) -> Result<String, error::Error> {
+        let fuse = fuse_rust::Fuse::default();
+        let res = fuse.search_text_in_iterable(scene, self.scenes.iter());
+
+        let scene = if !res.is_empty() {
+            &self.scenes[res[0].index]
+        } else {
+            scene
+        };
+
+        self.request(Request::Switch(scene.to_owned())).await?;
+
+        Ok(scene.to_owned())
+    }
+
+    async fn start_streaming(&self) -> Result<(), error::Error> {
+        self.request(Request::Start).await
+    }
+
+    async fn stop_streaming(&self) -> Result<(), error::Error> {
+        self.request(Request::Stop).await
+    }
+
+    /// Restarts the process
+    async fn fix(&self) -> Result<(), error::Error> {
+        self.request(Request::Restart).await
+    }
+
+    async fn toggle_recording(&self) -> Result<(), error::Error> {
+        Err(error::Error::Pipeline(
+            "Recording isn't supported".to_string(),
+        ))
+    }
+
+    async fn is_recording(&self) -> Result<bool, error::Error> {
+        Ok(false)
+    }
+
+    /// The process doesn't report any telemetry, only if it's running
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        let started = *self.started.lock().unwrap();
+
+        Ok(SoftwareStats {
+            streaming: Some(started.is_some()),
+            uptime: started.map(|started| started.elapsed().as_secs()),
+            ..Default::default()
+        })
+    }
+}
+
+impl Drop for Pipeline {
+    // Abort the spawned task, which kills the process
+    fn drop(&mut self) {
+        self.supervisor_join.abort();
+    }
+}
+
+/// Owns the process, starts it again when it exits while streaming
+struct Supervisor {
+    config: PipelineConfig,
+    state: noalbs::UserState,
+    scene: String,
+    streaming: bool,
+    child: Option<Child>,
+
+    /// When the running process started, shared with the pipeline
+    started: Arc<Mutex<Option<Instant>>>,
+
+    restart_at: Option<Instant>,
+
+    #[cfg(test)]
+    spawned: mpsc::UnboundedSender<Vec<String>>,
+}
+
+impl Supervisor {
+    async fn run(
+        mut self,
+        mut requests: mpsc::Receiver<(Request, oneshot::Sender<Result<(), error::Error>>)>,
+    ) {
+        {
+            let state = &mut self.state.write().await;
+            let bs = &mut state.broadcasting_software;
+            bs.current_scene = self.scene.to_owned();
+            bs.status = ClientStatus::Connected;
+            bs.connected_notifier().notify_waiters();
+        }
+
+        loop {
+            let restart_at = self.restart_at.unwrap_or_else(Instant::now);
+
+            tokio::select! {
+                request = requests.recv() => {
+                    let (request, reply) = match request {
+                        Some(request) => request,
+                        None => break,
+                    };
+
+                    let _ = reply.send(self.handle(request).await);
+
+                    // The switcher can still hold the state lock while
+                    // waiting for the reply
+                    self.sync_state().await;
+                }
+                status = exited(&mut self.child) => {
+                    match status {
+                        Ok(status) => warn!("Pipeline exited with {}", status),
+                        Err(e) => error!("Error waiting for the pipeline: {}", e),
+                    }
+
+                    self.child = None;
+                    *self.started.lock().unwrap() = None;
+                    self.restart_at = Some(Instant::now() + RESTART_DELAY);
+                }
+                _ = tokio::time::sleep_until(restart_at), if self.restart_at.is_some() => {
+                    self.restart_at = None;
+
+                    if let Err(e) = self.spawn() {
+                        error!("Error starting the pipeline: {}", e);
+                        self.restart_at = Some(Instant::now() + RESTART_DELAY);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn handle(&mut self, request: Request) -> Result<(), error::Error> {
+        match request {
+            Request::Switch(scene) => {
+                if scene == self.scene {
+                    return Ok(());
+                }
+
+                if !self.config.scenes.contains_key(&scene) {
+                    return Err(error::Error::Pipeline(format!(
+                        "No input for scene {}",
+                        scene
+                    )));
+                }
+
+                self.scene = scene;
+
+                if self.streaming {
+                    self.restart().await?;
+                }
+            }
+            Request::Start => {
+                if !self.streaming {
+                    self.streaming = true;
+                    self.restart().await?;
+                }
+            }
+            Request::Stop => {
+                self.streaming = false;
+                self.restart_at = None;
+                self.kill().await;
+            }
+            Request::Restart => {
+                if self.streaming {
+                    self.restart().await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the scene and streaming status to the user state like the
+    /// events of OBS would
+    async fn sync_state(&self) {
+        let state = &mut self.state.write().await;
+        let switchable = state.switcher_state.switchable_scenes.contains(&self.scene);
+        let bs = &mut state.broadcasting_software;
+
+        if bs.current_scene != self.scene {
+            bs.current_scene = self.scene.to_owned();
+
+            if switchable {
+                bs.switch_scene_notifier().notify_waiters();
+            }
+        }
+
+        if bs.is_streaming != self.streaming {
+            bs.is_streaming = self.streaming;
+
+            if self.streaming {
+                bs.last_stream_started_at = std::time::Instant::now();
+                bs.start_streaming_notifier().notify_waiters();
+            }
+        }
+    }
+
+    /// Starts the process again, keeps retrying when that fails
+    async fn restart(&mut self) -> Result<(), error::Error> {
+        self.kill().await;
+        self.restart_at = None;
+
+        let res = self.spawn();
+
+        if res.is_err() {
+            self.restart_at = Some(Instant::now() + RESTART_DELAY);
+        }
+
+        res
+    }
+
+    async fn kill(&mut self) {
+        *self.started.lock().unwrap() = None;
+
+        if let Some(mut child) = self.child.take() {
+            if let Err(e) = child.kill().await {
+                error!("Error stopping the pipeline: {}", e);
+            }
+        }
+    }
+
+    fn spawn(&mut self) -> Result<(), error::Error> {
+        let input =
+            self.config.scenes.get(&self.scene).ok_or_else(|| {
+                error::Error::Pipeline(format!("No input for scene {}", self.scene))
+            })?;
+
+        let args: Vec<String> = self
+            .config
+            .command
+            .iter()
+            .map(|arg| {
+                arg.replace("{input}", input)
+                    .replace("{output}", &self.config.output)
+            })
+            .collect();
+
+        let (program, args) = args
+            .split_first()
+            .ok_or_else(|| error::Error::Pipeline("Command is empty".to_string()))?;
+
+        info!("Starting the pipeline for scene {}", self.scene);
+
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        self.child = Some(child);
+        *self.started.lock().unwrap() = Some(Instant::now());
+
+        #[cfg(test)]
+        let _ = self.spawned.send(args.to_vec());
+
+        Ok(())
+    }
+}
+
+/// Waits for the process to exit, never finishes when there is none
+async fn exited(child: &mut Option<Child>) -> std::io::Result<std::process::ExitStatus> {
+    match child {
+        Some(child) => child.wait().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::broadcasting_software::fake_obs::user_state;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn config(script: &str) -> PipelineConfig {
+        let scenes = [
+            ("live", "feed"),
+            ("low", "low-slate"),
+            ("offline", "offline-slate"),
+        ];
+
+        PipelineConfig {
+            output: "srt://127.0.0.1:9000".to_string(),
+            scenes: scenes
+                .iter()
+                .map(|(scene, input)| (scene.to_string(), input.to_string()))
+                .collect::<HashMap<_, _>>(),
+            command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+        }
+    }
+
+    /// Waits for the next process to start, returns its script
+    async fn started(pipeline: &mut Pipeline) -> String {
+        let args = tokio::time::timeout(WAIT, pipeline.spawned.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        args.last().unwrap().to_owned()
+    }
+
+    #[tokio::test]
+    async fn switches_inputs() {
+        let state = user_state();
+        let mut pipeline =
+            Pipeline::new(config(": {input} {output}; exec sleep 60"), state.clone());
+
+        pipeline.start_streaming().await.unwrap();
+        assert_eq!(
+            ": offline-slate srt://127.0.0.1:9000; exec sleep 60",
+            started(&mut pipeline).await
+        );
+        assert_eq!(Some(true), pipeline.stats().await.unwrap().streaming);
+
+        let switched = state
+            .read()
+            .await
+            .broadcasting_software
+            .switch_scene_notifier();
+        let notified = switched.notified();
+
+        assert_eq!("live", pipeline.switch_scene("Live").await.unwrap());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            ": feed srt://127.0.0.1:9000; exec sleep 60",
+            started(&mut pipeline).await
+        );
+        assert_eq!(
+            "live",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        pipeline.stop_streaming().await.unwrap();
+        pipeline.switch_scene("low").await.unwrap();
+
+        let stats = pipeline.stats().await.unwrap();
+        assert_eq!(Some(false), stats.streaming);
+        assert_eq!(None, stats.uptime);
+
+        // Not streaming, so nothing got started before the switch replied
+        assert!(pipeline.spawned.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn restarts_exited_process() {
+        let mut pipeline = Pipeline::new(config("exit 1"), user_state());
+
+        pipeline.start_streaming().await.unwrap();
+
+        assert_eq!("exit 1", started(&mut pipeline).await);
+        assert_eq!("exit 1", started(&mut pipeline).await);
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::{Arc, Mutex};
+
+#[cfg(test)]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_trait::async_trait;
+
+use super::{stats::SoftwareStats, BroadcastingSoftwareLogic};
+use crate::error::Error;
+
+/// Broadcasting software that doesn't connect to anything, it only remembers
+/// the scene it got asked to switch to. Used when replaying a recording
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    switched: Arc<Mutex<Option<String>>>,
+
+    #[cfg(test)]
+    fail_next_switch: Arc<AtomicBool>,
+}
+
+impl Replay {
+    /// The scene switched to since the last call
+    pub fn take_switched(&self) -> Option<String> {
+        self.switched.lock().unwrap().take()
+    }
+
+    /// Makes the next switch fail like an unreachable OBS would
+    #[cfg(test)]
+    pub fn fail_next_switch(&self) {
+        self.fail_next_switch.store(true, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for Replay {
+    async fn switch_scene(&self, scene: &str) -> Result<String, Error> {
+        #[cfg(test)]
+        if self.fail_next_switch.swap(false, Ordering::SeqCst) {
+            return Err(Error::UnableInitialConnection);
+        }
+
+        *self.switched.lock().unwrap() = Some(scene.to_owned());
+
+        Ok(scene.to_owned())
+    }
+
+    async fn start_streaming(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn stop_streaming(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn toggle_recording(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn is_recording(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    async fn fix(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn stats(&self) -> Result<SoftwareStats, Error> {
+        Ok(SoftwareStats::default())
+    }
+}
@@ -0,0 +1,711 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, oneshot, Mutex},
+};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tracing::{error, info, warn};
+
+use crate::{config, error, noalbs, state::ClientStatus};
+
+use super::{
+    stats::{Frames, SoftwareStats},
+    BroadcastingSoftwareLogic,
+};
+
+/// Connection to the JSON-RPC websocket API of Streamlabs Desktop
+pub struct Slobs {
+    connection: Arc<Mutex<Option<Client>>>,
+    connection_join: tokio::task::JoinHandle<()>,
+    event_join: tokio::task::JoinHandle<()>,
+}
+
+impl Slobs {
+    pub fn new(connection_info: config::SlobsConfig, state: noalbs::UserState) -> Self {
+        // Streamlabs connection will be held in this arc mutex
+        let connection = Arc::new(Mutex::new(None));
+
+        // Will be used to receive events from Streamlabs
+        let (event_tx, event_rx) = mpsc::channel(100);
+
+        let connection_inner = connection.clone();
+        let state_inner = state.clone();
+        let connection_join = tokio::spawn(async move {
+            let connection = InnerConnection {
+                connection_info,
+                state: state_inner,
+                connection: connection_inner,
+                event_sender: event_tx,
+            };
+
+            connection.run().await;
+        });
+
+        let event_join = tokio::spawn(Self::event_handler(event_rx, state));
+
+        Self {
+            connection,
+            connection_join,
+            event_join,
+        }
+    }
+
+    async fn event_handler(mut events: mpsc::Receiver<Event>, state: noalbs::UserState) {
+        while let Some(event) = events.recv().await {
+            match event.resource_id.as_str() {
+                "ScenesService.sceneSwitched" => {
+                    let scene_name = match event.data["name"].as_str() {
+                        Some(name) => name.to_owned(),
+                        None => continue,
+                    };
+
+                    let mut l = state.write().await;
+
+                    let switchable = &l.switcher_state.switchable_scenes;
+                    if switchable.contains(&scene_name) {
+                        l.broadcasting_software
+                            .switch_scene_notifier()
+                            .notify_waiters();
+                    }
+
+                    l.broadcasting_software.current_scene = scene_name;
+                }
+                "StreamingService.streamingStatusChange" => match event.data.as_str() {
+                    Some("live") => {
+                        let mut l = state.write().await;
+                        l.broadcasting_software.is_streaming = true;
+                        l.broadcasting_software.last_stream_started_at = std::time::Instant::now();
+
+                        l.broadcasting_software
+                            .start_streaming_notifier()
+                            .notify_waiters();
+                    }
+                    Some("offline") => {
+                        let mut l = state.write().await;
+                        l.broadcasting_software.is_streaming = false;
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            }
+        }
+    }
+
+    async fn request<T>(&self, method: &str, resource: &str, args: Value) -> Result<T, error::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let connection = self.connection.lock().await;
+
+        let client = match &*connection {
+            Some(client) => client,
+            None => return Err(error::Error::UnableInitialConnection),
+        };
+
+        client.request(method, resource, args).await
+    }
+
+    async fn streaming_model(&self) -> Result<StreamingModel, error::Error> {
+        self.request("getModel", "StreamingService", json!([]))
+            .await
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for Slobs {
+    async fn switch_scene(&self, scene: &str) -> Result<String, error::Error> {
+        let scenes: Vec<Scene> = self
+            .request("getScenes", "ScenesService", json!([]))
+            .await?;
+        let names: Vec<&str> = scenes.iter().map(|s| s.name.as_str()).collect();
+
+        let fuse = fuse_rust::Fuse::default();
+        let res = fuse.search_text_in_iterable(scene, names.iter());
+
+        let scene = if !res.is_empty() {
+            &scenes[res[0].index]
+        } else {
+            scenes
+                .iter()
+                .find(|s| s.name == scene)
+                .ok_or_else(|| error::Error::SlobsRequest(format!("No scene {}", scene)))?
+        };
+
+        self.request::<Value>("makeSceneActive", "ScenesService", json!([scene.id]))
+            .await?;
+
+        Ok(scene.name.to_owned())
+    }
+
+    async fn start_streaming(&self) -> Result<(), error::Error> {
+        if self.streaming_model().await?.streaming_status == "offline" {
+            self.request::<Value>("toggleStreaming", "StreamingService", json!([]))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn stop_streaming(&self) -> Result<(), error::Error> {
+        if self.streaming_model().await?.streaming_status != "offline" {
+            self.request::<Value>("toggleStreaming", "StreamingService", json!([]))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Restarts the media sources that pull a RTMP or SRT stream by
+    /// updating them with their own settings
+    async fn fix(&self) -> Result<(), error::Error> {
+        let sources: Vec<Source> = self
+            .request("getSources", "SourcesService", json!([]))
+            .await?;
+
+        for source in sources.iter().filter(|s| s.kind == "ffmpeg_source") {
+            let settings: Value = self
+                .request("getSettings", &source.resource_id, json!([]))
+                .await?;
+
+            let input = settings["input"]
+                .as_str()
+                .unwrap_or_default()
+                .to_lowercase();
+
+            if !input.starts_with("rtmp") && !input.starts_with("srt") {
+                continue;
+            }
+
+            self.request::<Value>("updateSettings", &source.resource_id, json!([settings]))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn toggle_recording(&self) -> Result<(), error::Error> {
+        self.request::<Value>("toggleRecording", "StreamingService", json!([]))
+            .await?;
+        Ok(())
+    }
+
+    async fn is_recording(&self) -> Result<bool, error::Error> {
+        Ok(self.streaming_model().await?.recording_status == "recording")
+    }
+
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        let streaming = self.streaming_model().await?;
+        let performance: PerformanceModel = self
+            .request("getModel", "PerformanceService", json!([]))
+            .await?;
+
+        let mut stats = SoftwareStats {
+            cpu_usage: Some(performance.cpu),
+            fps: Some(performance.frame_rate),
+            ..Default::default()
+        };
+
+        if streaming.streaming_status == "live" {
+            stats.streaming_duration = streaming
+                .streaming_status_time
+                .as_deref()
+                .and_then(seconds_since);
+            stats.bitrate = Some(performance.streaming_bandwidth.round() as u64);
+            stats.dropped_frames = Some(Frames {
+                count: performance.number_dropped_frames,
+                percentage: performance.percentage_dropped_frames,
+            });
+        }
+
+        if streaming.recording_status == "recording" {
+            stats.recording_duration = streaming
+                .recording_status_time
+                .as_deref()
+                .and_then(seconds_since);
+        }
+
+        Ok(stats)
+    }
+}
+
+impl Drop for Slobs {
+    // Abort the spawned tasks
+    fn drop(&mut self) {
+        self.connection_join.abort();
+        self.event_join.abort();
+    }
+}
+
+/// The real connection to Streamlabs, automatically keeps trying to connect.
+struct InnerConnection {
+    connection_info: config::SlobsConfig,
+    state: noalbs::UserState,
+    connection: Arc<Mutex<Option<Client>>>,
+    event_sender: mpsc::Sender<Event>,
+}
+
+impl InnerConnection {
+    async fn run(&self) {
+        loop {
+            let (client, mut events) = self.get_client().await;
+
+            if let Err(e) = self.set_connected(&client).await {
+                error!("Error getting the Streamlabs status: {}", e);
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                continue;
+            }
+
+            {
+                let mut connection = self.connection.lock().await;
+                *connection = Some(client);
+            }
+
+            while let Some(event) = events.recv().await {
+                let _ = self.event_sender.send(event).await;
+            }
+
+            warn!("Disconnected");
+
+            {
+                let state = &mut self.state.write().await;
+                let bs = &mut state.broadcasting_software;
+                bs.status = ClientStatus::Disconnected;
+                bs.is_streaming = false;
+            }
+        }
+    }
+
+    async fn set_connected(&self, client: &Client) -> Result<(), error::Error> {
+        let scene: Scene = client
+            .request("activeScene", "ScenesService", json!([]))
+            .await?;
+        let streaming: StreamingModel = client
+            .request("getModel", "StreamingService", json!([]))
+            .await?;
+
+        let state = &mut self.state.write().await;
+        let bs = &mut state.broadcasting_software;
+
+        bs.current_scene = scene.name;
+        bs.is_streaming = streaming.streaming_status == "live";
+        bs.status = ClientStatus::Connected;
+
+        let bs = &state.broadcasting_software;
+        bs.connected_notifier().notify_waiters();
+
+        if bs.is_streaming {
+            bs.start_streaming_notifier().notify_waiters();
+        }
+
+        if state
+            .switcher_state
+            .switchable_scenes
+            .contains(&bs.current_scene)
+        {
+            bs.switch_scene_notifier().notify_waiters();
+        }
+
+        Ok(())
+    }
+
+    /// Attempts to connect to Streamlabs
+    ///
+    /// Blocks until a successful connection has been established.
+    /// An exponential backoff strategy is used to keep retrying to connect.
+    /// This will grow until the 5th retry failure after which the max seconds
+    /// will be reached of 32 seconds.
+    async fn get_client(&self) -> (Client, mpsc::UnboundedReceiver<Event>) {
+        let mut retry_grow = 1;
+
+        loop {
+            info!("Connecting");
+            let info = &self.connection_info;
+
+            match Client::connect(&info.host, info.port, info.token.as_deref()).await {
+                Ok(connected) => {
+                    info!("Connected");
+                    break connected;
+                }
+                Err(error::Error::SlobsAuthFailed) => {
+                    error!("Can't authenticate");
+                    info!("trying to connect again in {} seconds", 10);
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    continue;
+                }
+                Err(e) => error!("Error while trying to connect to Streamlabs: {}", e),
+            };
+
+            let wait = 1 << retry_grow;
+            warn!("Unable to connect");
+            info!("trying to connect again in {} seconds", wait);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+
+            if retry_grow < 5 {
+                retry_grow += 1;
+            }
+        }
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type PendingRequests = Arc<std::sync::Mutex<HashMap<u64, oneshot::Sender<Response>>>>;
+
+/// An authorized JSON-RPC session with subscriptions to the scene and
+/// streaming status changes
+struct Client {
+    sink: Mutex<SplitSink<Socket, Message>>,
+    pending: PendingRequests,
+    next_id: AtomicU64,
+    read_join: tokio::task::JoinHandle<()>,
+}
+
+impl Client {
+    /// Connects, authorizes and subscribes, the receiver gets the events
+    /// till the connection closes
+    async fn connect(
+        host: &str,
+        port: u16,
+        token: Option<&str>,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Event>), error::Error> {
+        // The raw websocket endpoint of the SockJS server
+        let url = format!("ws://{}:{}/api/websocket", host, port);
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let (sink, stream) = socket.split();
+
+        let pending = PendingRequests::default();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+
+        let read_join = tokio::spawn(Self::read_loop(stream, pending.clone(), event_tx));
+
+        let client = Self {
+            sink: Mutex::new(sink),
+            pending,
+            next_id: AtomicU64::new(1),
+            read_join,
+        };
+
+        if let Some(token) = token {
+            client
+                .request::<Value>("auth", "TcpServerService", json!([token]))
+                .await
+                .map_err(|_| error::Error::SlobsAuthFailed)?;
+        }
+
+        client
+            .request::<Value>("sceneSwitched", "ScenesService", json!([]))
+            .await?;
+        client
+            .request::<Value>("streamingStatusChange", "StreamingService", json!([]))
+            .await?;
+
+        Ok((client, event_rx))
+    }
+
+    /// Sends responses to the requests waiting for them and events to the
+    /// receiver. Ends when the connection closes
+    async fn read_loop(
+        mut stream: SplitStream<Socket>,
+        pending: PendingRequests,
+        events: mpsc::UnboundedSender<Event>,
+    ) {
+        while let Ok(response) = next_response(&mut stream).await {
+            let id = match response.id {
+                Some(id) => id,
+                None => {
+                    let event = response
+                        .result
+                        .and_then(|r| serde_json::from_value::<Event>(r).ok());
+
+                    if let Some(event) = event.filter(|e| e.kind == "EVENT") {
+                        let _ = events.send(event);
+                    }
+
+                    continue;
+                }
+            };
+
+            let waiting = pending.lock().unwrap().remove(&id);
+
+            if let Some(waiting) = waiting {
+                let _ = waiting.send(response);
+            }
+        }
+
+        // Fails the requests that are still waiting
+        pending.lock().unwrap().clear();
+    }
+
+    async fn request<T>(&self, method: &str, resource: &str, args: Value) -> Result<T, error::Error>
+    where
+        T: DeserializeOwned,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+
+        self.pending.lock().unwrap().insert(id, tx);
+
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": {
+                "resource": resource,
+                "args": args
+            }
+        });
+
+        if let Err(e) = self
+            .sink
+            .lock()
+            .await
+            .send(Message::Text(request.to_string()))
+            .await
+        {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e.into());
+        }
+
+        let response = rx
+            .await
+            .map_err(|_| error::Error::SlobsRequest("Connection closed".to_string()))?;
+
+        if let Some(error) = response.error {
+            return Err(error::Error::SlobsRequest(format!(
+                "{}.{} failed: {}",
+                resource, method, error.message
+            )));
+        }
+
+        Ok(serde_json::from_value(
+            response.result.unwrap_or(Value::Null),
+        )?)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.read_join.abort();
+    }
+}
+
+async fn next_response(stream: &mut SplitStream<Socket>) -> Result<Response, error::Error> {
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Text(text) => return Ok(serde_json::from_str(&text)?),
+            Message::Close(_) => break,
+            _ => continue,
+        }
+    }
+
+    Err(error::Error::SlobsRequest("Connection closed".to_string()))
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: Option<u64>,
+    result: Option<Value>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+/// Result of a response without id
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    #[serde(rename = "_type")]
+    pub kind: String,
+
+    pub resource_id: String,
+
+    #[serde(default)]
+    pub data: Value,
+}
+
+#[derive(Deserialize)]
+struct Scene {
+    id: String,
+    name: String,
+}
+
+/// Seconds since a status change time like 2022-05-01T20:03:12.345Z
+fn seconds_since(time: &str) -> Option<u64> {
+    let time = chrono::DateTime::parse_from_rfc3339(time).ok()?;
+    let seconds = chrono::Utc::now().signed_duration_since(time).num_seconds();
+
+    Some(seconds.max(0) as u64)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamingModel {
+    streaming_status: String,
+    recording_status: String,
+    streaming_status_time: Option<String>,
+    recording_status_time: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PerformanceModel {
+    #[serde(rename = "CPU")]
+    cpu: f64,
+    number_dropped_frames: u64,
+
+    /// Already in percent
+    percentage_dropped_frames: f64,
+
+    /// Kbps
+    streaming_bandwidth: f64,
+    frame_rate: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Source {
+    resource_id: String,
+
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcasting_software::fake_obs::{user_state, FakeObs};
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn slobs_config(fake: &FakeObs, token: Option<&str>) -> config::SlobsConfig {
+        let obs = fake.config();
+
+        config::SlobsConfig {
+            host: obs.host,
+            port: obs.port,
+            token: token.map(str::to_owned),
+        }
+    }
+
+    async fn connect(connection_info: config::SlobsConfig, state: &noalbs::UserState) -> Slobs {
+        let connected = state
+            .read()
+            .await
+            .broadcasting_software
+            .connected_notifier();
+        let notified = connected.notified();
+
+        let slobs = Slobs::new(connection_info, state.clone());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        slobs
+    }
+
+    #[tokio::test]
+    async fn follows_scenes() {
+        let fake = FakeObs::start_slobs(&["intro", "live", "low"], Some("token")).await;
+        let state = user_state();
+
+        let slobs = connect(slobs_config(&fake, Some("token")), &state).await;
+
+        assert_eq!(
+            "intro",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        let switched = state
+            .read()
+            .await
+            .broadcasting_software
+            .switch_scene_notifier();
+        let notified = switched.notified();
+
+        fake.switch_scene("live");
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            "live",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        assert_eq!("low", slobs.switch_scene("low").await.unwrap());
+        assert_eq!("low", fake.current_scene());
+
+        let started = state
+            .read()
+            .await
+            .broadcasting_software
+            .start_streaming_notifier();
+        let notified = started.notified();
+
+        slobs.start_streaming().await.unwrap();
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert!(state.read().await.broadcasting_software.is_streaming);
+
+        // Already streaming, so it doesn't toggle
+        slobs.start_streaming().await.unwrap();
+        assert_eq!(1, fake.received("toggleStreaming").len());
+
+        slobs.toggle_recording().await.unwrap();
+        assert!(slobs.is_recording().await.unwrap());
+
+        let stats = slobs.stats().await.unwrap();
+        assert!(stats.streaming_duration.unwrap() >= 60);
+        assert!(stats.recording_duration.is_some());
+        assert_eq!(Some(6000), stats.bitrate);
+        assert_eq!(Some(12), stats.dropped_frames.map(|f| f.count));
+        assert_eq!(Some(60.0), stats.fps);
+    }
+
+    #[tokio::test]
+    async fn wrong_token() {
+        let fake = FakeObs::start_slobs(&["live"], Some("token")).await;
+
+        let res = Client::connect("127.0.0.1", fake.config().port, Some("wrong")).await;
+        assert!(matches!(res, Err(error::Error::SlobsAuthFailed)));
+    }
+
+    #[tokio::test]
+    async fn fix_updates_stream_sources() {
+        let fake = FakeObs::start_slobs(&["live"], None).await;
+        fake.add_media_source(
+            "feed",
+            "ffmpeg_source",
+            json!({ "input": "srt://127.0.0.1:8282", "is_local_file": false }),
+        );
+        fake.add_media_source(
+            "intro video",
+            "ffmpeg_source",
+            json!({ "local_file": "/videos/intro.mp4", "is_local_file": true }),
+        );
+
+        let slobs = connect(slobs_config(&fake, None), &user_state()).await;
+        slobs.fix().await.unwrap();
+
+        let updated = fake.received("updateSettings");
+        assert_eq!(1, updated.len());
+        assert_eq!(json!("Source[\"feed\"]"), updated[0]["resource"]);
+        assert_eq!(
+            json!("srt://127.0.0.1:8282"),
+            updated[0]["args"][0]["input"]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use rust_i18n::t;
+use serde::Serialize;
+
+use crate::stream_servers::stats::format_duration;
+
+/// Telemetry of the broadcasting software, fields are None when the
+/// software doesn't report them
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoftwareStats {
+    /// Whether it's streaming, for software that doesn't report the duration
+    pub streaming: Option<bool>,
+
+    /// Whether it's recording, for software that doesn't report the duration
+    pub recording: Option<bool>,
+
+    /// Seconds since the stream started, None when not streaming
+    pub streaming_duration: Option<u64>,
+
+    /// Seconds since the recording started, None when not recording
+    pub recording_duration: Option<u64>,
+
+    /// Seconds since the output process started, None when it isn't running
+    pub uptime: Option<u64>,
+
+    /// Output bitrate in Kbps
+    pub bitrate: Option<u64>,
+
+    /// Frames dropped because of the network
+    pub dropped_frames: Option<Frames>,
+
+    /// Frames skipped by the encoder
+    pub skipped_frames: Option<Frames>,
+
+    /// Frames missed while rendering
+    pub lagged_frames: Option<Frames>,
+
+    /// CPU usage of the software in percent
+    pub cpu_usage: Option<f64>,
+
+    pub fps: Option<f64>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Frames {
+    pub count: u64,
+
+    /// Percentage of all frames
+    pub percentage: f64,
+}
+
+impl Frames {
+    pub fn new(count: u64, total: u64) -> Self {
+        let percentage = if total == 0 {
+            0.0
+        } else {
+            count as f64 / total as f64 * 100.0
+        };
+
+        Self { count, percentage }
+    }
+}
+
+impl SoftwareStats {
+    pub fn obs_info(&self, lang: &str) -> String {
+        let mut info = Vec::new();
+
+        match (self.streaming_duration, self.streaming) {
+            (Some(duration), _) => info.push(t!(
+                "obsinfo.streaming",
+                locale = lang,
+                duration = &format_duration(duration)
+            )),
+            (None, Some(true)) => info.push(t!("obsinfo.streamingNow", locale = lang)),
+            (None, Some(false)) => info.push(t!("obsinfo.notStreaming", locale = lang)),
+            (None, None) => {}
+        }
+
+        match (self.recording_duration, self.recording) {
+            (Some(duration), _) => info.push(t!(
+                "obsinfo.recording",
+                locale = lang,
+                duration = &format_duration(duration)
+            )),
+            (None, Some(true)) => info.push(t!("obsinfo.recordingNow", locale = lang)),
+            (None, Some(false)) => info.push(t!("obsinfo.notRecording", locale = lang)),
+            (None, None) => {}
+        }
+
+        if let Some(uptime) = self.uptime {
+            info.push(t!(
+                "obsinfo.uptime",
+                locale = lang,
+                duration = &format_duration(uptime)
+            ));
+        }
+
+        if let Some(bitrate) = self.bitrate {
+            info.push(t!(
+                "obsinfo.bitrate",
+                locale = lang,
+                bitrate = &bitrate.to_string()
+            ));
+        }
+
+        let frames = [
+            ("obsinfo.dropped", self.dropped_frames),
+            ("obsinfo.skipped", self.skipped_frames),
+            ("obsinfo.lagged", self.lagged_frames),
+        ];
+
+        for (key, frames) in frames.iter().filter_map(|(key, f)| Some((*key, (*f)?))) {
+            info.push(t!(
+                key,
+                locale = lang,
+                count = &frames.count.to_string(),
+                percentage = &format!("{:.1}", frames.percentage)
+            ));
+        }
+
+        if let Some(cpu) = self.cpu_usage {
+            info.push(t!(
+                "obsinfo.cpu",
+                locale = lang,
+                cpu = &format!("{:.1}", cpu)
+            ));
+        }
+
+        if let Some(fps) = self.fps {
+            info.push(t!(
+                "obsinfo.fps",
+                locale = lang,
+                fps = &fps.round().to_string()
+            ));
+        }
+
+        if info.is_empty() {
+            return t!("obsinfo.notSupported", locale = lang);
+        }
+
+        info.join(" | ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn obs_info_message() {
+        let stats = SoftwareStats {
+            streaming_duration: Some(3725),
+            bitrate: Some(6012),
+            dropped_frames: Some(Frames::new(12, 4800)),
+            skipped_frames: Some(Frames::new(0, 4800)),
+            cpu_usage: Some(12.34),
+            fps: Some(59.94),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "streaming for 1:02:05 | 6012 Kbps | 12 dropped frames (0.2%) | 0 skipped frames (0.0%) | 12.3% CPU | 60 fps",
+            stats.obs_info("en")
+        );
+    }
+
+    #[test]
+    fn obs_info_without_telemetry() {
+        assert_eq!(
+            "The broadcasting software doesn't report any stats",
+            SoftwareStats::default().obs_info("en")
+        );
+
+        let stats = SoftwareStats {
+            streaming: Some(true),
+            recording: Some(false),
+            uptime: Some(65),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "streaming | not recording | running for 0:01:05",
+            stats.obs_info("en")
+        );
+    }
+}
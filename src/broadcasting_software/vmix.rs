@@ -0,0 +1,602 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex as StdMutex},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpStream,
+    },
+    sync::{mpsc, oneshot, Mutex},
+};
+use tracing::{error, info, warn};
+
+use crate::{config::VmixConfig, error, noalbs, state::ClientStatus};
+
+use super::{stats::SoftwareStats, BroadcastingSoftwareLogic};
+
+/// Connection to the TCP API of vMix, the inputs are used as scenes
+pub struct Vmix {
+    connection: Arc<Mutex<Option<Arc<Client>>>>,
+    connection_join: tokio::task::JoinHandle<()>,
+}
+
+impl Vmix {
+    pub fn new(connection_info: VmixConfig, state: noalbs::UserState) -> Self {
+        let connection = Arc::new(Mutex::new(None));
+
+        let connection_inner = connection.clone();
+        let connection_join = tokio::spawn(async move {
+            let connection = InnerConnection {
+                connection_info,
+                state,
+                connection: connection_inner,
+            };
+
+            connection.run().await;
+        });
+
+        Self {
+            connection,
+            connection_join,
+        }
+    }
+
+    async fn client(&self) -> Result<Arc<Client>, error::Error> {
+        self.connection
+            .lock()
+            .await
+            .to_owned()
+            .ok_or(error::Error::UnableInitialConnection)
+    }
+}
+
+#[async_trait]
+impl BroadcastingSoftwareLogic for Vmix {
+    async fn switch_scene(&self, scene: &str) -> Result<String, error::Error> {
+        let client = self.client().await?;
+        let inputs: Vec<String> = client
+            .state()
+            .await?
+            .inputs
+            .input
+            .into_iter()
+            .map(|i| i.title)
+            .collect();
+
+        let fuse = fuse_rust::Fuse::default();
+        let res = fuse.search_text_in_iterable(scene, inputs.iter());
+
+        let scene = if !res.is_empty() {
+            &inputs[res[0].index]
+        } else {
+            scene
+        };
+
+        client
+            .function("CutDirect", &format!("Input={}", encode(scene)))
+            .await?;
+
+        Ok(scene.to_owned())
+    }
+
+    async fn start_streaming(&self) -> Result<(), error::Error> {
+        self.client().await?.function("StartStreaming", "").await
+    }
+
+    async fn stop_streaming(&self) -> Result<(), error::Error> {
+        self.client().await?.function("StopStreaming", "").await
+    }
+
+    /// Restarts the stream inputs
+    async fn fix(&self) -> Result<(), error::Error> {
+        let client = self.client().await?;
+        let state = client.state().await?;
+
+        let streams = state
+            .inputs
+            .input
+            .iter()
+            .filter(|i| i.kind == "Stream" || i.kind == "SRT");
+
+        for input in streams {
+            client
+                .function("Restart", &format!("Input={}", input.key))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn toggle_recording(&self) -> Result<(), error::Error> {
+        self.client()
+            .await?
+            .function("StartStopRecording", "")
+            .await
+    }
+
+    async fn is_recording(&self) -> Result<bool, error::Error> {
+        Ok(self.client().await?.state().await?.is_recording())
+    }
+
+    /// The TCP API only reports if it's streaming and recording
+    async fn stats(&self) -> Result<SoftwareStats, error::Error> {
+        let vmix_state = self.client().await?.state().await?;
+
+        Ok(SoftwareStats {
+            streaming: Some(vmix_state.is_streaming()),
+            recording: Some(vmix_state.is_recording()),
+            ..Default::default()
+        })
+    }
+}
+
+impl Drop for Vmix {
+    // Abort the spawned task
+    fn drop(&mut self) {
+        self.connection_join.abort();
+    }
+}
+
+/// The real connection to vMix, automatically keeps trying to connect.
+struct InnerConnection {
+    connection_info: VmixConfig,
+    state: noalbs::UserState,
+    connection: Arc<Mutex<Option<Arc<Client>>>>,
+}
+
+impl InnerConnection {
+    async fn run(&self) {
+        loop {
+            let (client, mut activators) = self.get_client().await;
+            let client = Arc::new(client);
+
+            if let Err(e) = self.set_connected(&client).await {
+                error!("Error getting the vMix state: {}", e);
+                tokio::time::sleep(Duration::from_secs(10)).await;
+                continue;
+            }
+
+            {
+                let mut connection = self.connection.lock().await;
+                *connection = Some(client.clone());
+            }
+
+            while let Some(activator) = activators.recv().await {
+                if let Err(e) = self.handle_activator(&client, activator).await {
+                    error!("Error handling vMix activator: {}", e);
+                }
+            }
+
+            warn!("Disconnected");
+
+            {
+                let state = &mut self.state.write().await;
+                let bs = &mut state.broadcasting_software;
+                bs.status = ClientStatus::Disconnected;
+                bs.is_streaming = false;
+            }
+        }
+    }
+
+    async fn set_connected(&self, client: &Client) -> Result<(), error::Error> {
+        let vmix_state = client.state().await?;
+
+        let state = &mut self.state.write().await;
+        let bs = &mut state.broadcasting_software;
+
+        bs.current_scene = vmix_state.active_title().unwrap_or_default();
+        bs.is_streaming = vmix_state.is_streaming();
+        bs.status = ClientStatus::Connected;
+
+        let bs = &state.broadcasting_software;
+        bs.connected_notifier().notify_waiters();
+
+        if bs.is_streaming {
+            bs.start_streaming_notifier().notify_waiters();
+        }
+
+        if state
+            .switcher_state
+            .switchable_scenes
+            .contains(&bs.current_scene)
+        {
+            bs.switch_scene_notifier().notify_waiters();
+        }
+
+        Ok(())
+    }
+
+    async fn handle_activator(
+        &self,
+        client: &Client,
+        activator: Activator,
+    ) -> Result<(), error::Error> {
+        match activator {
+            Activator::Input(number) => {
+                let vmix_state = client.state().await?;
+
+                let scene_name = match vmix_state.title(number) {
+                    Some(title) => title,
+                    None => return Ok(()),
+                };
+
+                let mut l = self.state.write().await;
+
+                let switchable = &l.switcher_state.switchable_scenes;
+                if switchable.contains(&scene_name) {
+                    l.broadcasting_software
+                        .switch_scene_notifier()
+                        .notify_waiters();
+                }
+
+                l.broadcasting_software.current_scene = scene_name;
+            }
+            Activator::Streaming(true) => {
+                let mut l = self.state.write().await;
+                l.broadcasting_software.is_streaming = true;
+                l.broadcasting_software.last_stream_started_at = std::time::Instant::now();
+
+                l.broadcasting_software
+                    .start_streaming_notifier()
+                    .notify_waiters();
+            }
+            Activator::Streaming(false) => {
+                let mut l = self.state.write().await;
+                l.broadcasting_software.is_streaming = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Attempts to connect to vMix
+    ///
+    /// Blocks until a successful connection has been established.
+    /// An exponential backoff strategy is used to keep retrying to connect.
+    /// This will grow until the 5th retry failure after which the max seconds
+    /// will be reached of 32 seconds.
+    async fn get_client(&self) -> (Client, mpsc::UnboundedReceiver<Activator>) {
+        let mut retry_grow = 1;
+
+        loop {
+            info!("Connecting");
+            let info = &self.connection_info;
+
+            match Client::connect(&info.host, info.port).await {
+                Ok(connected) => {
+                    info!("Connected");
+                    break connected;
+                }
+                Err(e) => error!("Error while trying to connect to vMix: {}", e),
+            };
+
+            let wait = 1 << retry_grow;
+            warn!("Unable to connect");
+            info!("trying to connect again in {} seconds", wait);
+            tokio::time::sleep(Duration::from_secs(wait)).await;
+
+            if retry_grow < 5 {
+                retry_grow += 1;
+            }
+        }
+    }
+}
+
+/// Activators vMix sends after subscribing to ACTS
+#[derive(Debug, PartialEq)]
+enum Activator {
+    /// The input with this number went to program
+    Input(u32),
+    Streaming(bool),
+}
+
+type Pending = Arc<StdMutex<VecDeque<oneshot::Sender<String>>>>;
+
+/// A TCP API session, vMix responds to the commands in the order they were
+/// sent
+struct Client {
+    writer: Mutex<OwnedWriteHalf>,
+    pending: Pending,
+    read_join: tokio::task::JoinHandle<()>,
+}
+
+impl Client {
+    /// Connects and subscribes to the activators, the receiver gets them
+    /// till the connection closes
+    async fn connect(
+        host: &str,
+        port: u16,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<Activator>), error::Error> {
+        let stream = TcpStream::connect((host, port)).await?;
+        let (reader, writer) = stream.into_split();
+
+        let pending = Pending::default();
+        let (activator_tx, activator_rx) = mpsc::unbounded_channel();
+
+        let read_join = tokio::spawn(Self::read_loop(reader, pending.clone(), activator_tx));
+
+        let client = Self {
+            writer: Mutex::new(writer),
+            pending,
+            read_join,
+        };
+
+        let response = client.command("SUBSCRIBE ACTS").await?;
+
+        if !response.starts_with("SUBSCRIBE OK") {
+            return Err(error::Error::Vmix(response));
+        }
+
+        Ok((client, activator_rx))
+    }
+
+    /// Sends the responses to the commands waiting for them and the
+    /// activators to the receiver. Ends when the connection closes
+    async fn read_loop(
+        reader: OwnedReadHalf,
+        pending: Pending,
+        activators: mpsc::UnboundedSender<Activator>,
+    ) {
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+
+        loop {
+            line.clear();
+
+            match reader.read_line(&mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            let line = line.trim_end();
+
+            // XML responses give the length of the XML that follows
+            let xml_length = line
+                .strip_prefix("XML ")
+                .and_then(|length| length.parse::<usize>().ok());
+
+            let response = if let Some(length) = xml_length {
+                let mut xml = vec![0; length];
+
+                if reader.read_exact(&mut xml).await.is_err() {
+                    break;
+                }
+
+                String::from_utf8_lossy(&xml).into_owned()
+            } else {
+                let mut parts = line.splitn(3, ' ');
+
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some("ACTS"), Some("OK"), Some(activator)) => {
+                        if let Some(activator) = parse_activator(activator) {
+                            let _ = activators.send(activator);
+                        }
+
+                        continue;
+                    }
+                    (Some("FUNCTION" | "SUBSCRIBE" | "XML"), _, _) => line.to_owned(),
+                    _ => continue,
+                }
+            };
+
+            if let Some(waiting) = pending.lock().unwrap().pop_front() {
+                let _ = waiting.send(response);
+            }
+        }
+
+        // Fails the commands that are still waiting
+        pending.lock().unwrap().clear();
+    }
+
+    async fn command(&self, command: &str) -> Result<String, error::Error> {
+        let (tx, rx) = oneshot::channel();
+
+        {
+            let mut writer = self.writer.lock().await;
+            self.pending.lock().unwrap().push_back(tx);
+
+            writer
+                .write_all(format!("{}\r\n", command).as_bytes())
+                .await?;
+        }
+
+        rx.await
+            .map_err(|_| error::Error::Vmix("Connection closed".to_string()))
+    }
+
+    async fn function(&self, function: &str, query: &str) -> Result<(), error::Error> {
+        let response = self
+            .command(format!("FUNCTION {} {}", function, query).trim_end())
+            .await?;
+
+        if !response.starts_with("FUNCTION OK") {
+            return Err(error::Error::Vmix(response));
+        }
+
+        Ok(())
+    }
+
+    async fn state(&self) -> Result<VmixState, error::Error> {
+        let xml = self.command("XML").await?;
+
+        Ok(quick_xml::de::from_str(&xml)?)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.read_join.abort();
+    }
+}
+
+/// Parses activators like `Input 2 1` or `Streaming 0`, only inputs going
+/// to program are used
+fn parse_activator(activator: &str) -> Option<Activator> {
+    let parts: Vec<&str> = activator.split_whitespace().collect();
+
+    match parts.as_slice() {
+        ["Input", number, "1"] => Some(Activator::Input(number.parse().ok()?)),
+        ["Streaming", on] => Some(Activator::Streaming(*on == "1")),
+        _ => None,
+    }
+}
+
+/// Percent encodes a value of the function query
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[derive(Deserialize, Debug)]
+struct VmixState {
+    inputs: Inputs,
+    active: u32,
+    streaming: String,
+    recording: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Inputs {
+    #[serde(default)]
+    input: Vec<Input>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Input {
+    key: String,
+    number: u32,
+
+    #[serde(rename = "type")]
+    kind: String,
+
+    title: String,
+}
+
+impl VmixState {
+    fn title(&self, number: u32) -> Option<String> {
+        self.inputs
+            .input
+            .iter()
+            .find(|i| i.number == number)
+            .map(|i| i.title.to_owned())
+    }
+
+    fn active_title(&self) -> Option<String> {
+        self.title(self.active)
+    }
+
+    fn is_streaming(&self) -> bool {
+        self.streaming == "True"
+    }
+
+    fn is_recording(&self) -> bool {
+        self.recording == "True"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadcasting_software::{fake_obs::user_state, fake_vmix::FakeVmix};
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn parse_state() {
+        let xml = r#"<vmix>
+            <version>25.0.0.34</version>
+            <edition>HD</edition>
+            <inputs>
+                <input key="26cae087-b7b6-4d45-98e4-de03c0ce7bd2" number="1" type="Capture" title="Camera" state="Running">Camera</input>
+                <input key="9a3b6bb8-8a2b-4b3c-a6c5-1c6ec8c4f0a1" number="2" type="Stream" title="SRT feed" state="Running">SRT feed</input>
+            </inputs>
+            <active>2</active>
+            <preview>1</preview>
+            <recording>False</recording>
+            <streaming>True</streaming>
+        </vmix>"#;
+
+        let state: VmixState = quick_xml::de::from_str(xml).unwrap();
+
+        assert_eq!(Some("SRT feed".to_string()), state.active_title());
+        assert_eq!("Stream", state.inputs.input[1].kind);
+        assert!(state.is_streaming());
+        assert!(!state.is_recording());
+    }
+
+    #[test]
+    fn activators() {
+        assert_eq!(Some(Activator::Input(3)), parse_activator("Input 3 1"));
+        assert_eq!(None, parse_activator("Input 3 0"));
+        assert_eq!(
+            Some(Activator::Streaming(true)),
+            parse_activator("Streaming 1")
+        );
+        assert_eq!(None, parse_activator("Overlay1 2 1"));
+        assert_eq!("SRT%20feed%2F1", encode("SRT feed/1"));
+    }
+
+    #[tokio::test]
+    async fn follows_inputs() {
+        let fake = FakeVmix::start(&["intro", "live", "low"]).await;
+        let state = user_state();
+
+        let connected = state
+            .read()
+            .await
+            .broadcasting_software
+            .connected_notifier();
+        let notified = connected.notified();
+
+        let vmix = Vmix::new(fake.config(), state.clone());
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            "intro",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        let switched = state
+            .read()
+            .await
+            .broadcasting_software
+            .switch_scene_notifier();
+        let notified = switched.notified();
+
+        // Switched in vMix
+        fake.cut("live");
+        tokio::time::timeout(WAIT, notified).await.unwrap();
+
+        assert_eq!(
+            "live",
+            state.read().await.broadcasting_software.current_scene
+        );
+
+        assert_eq!("low", vmix.switch_scene("low").await.unwrap());
+        assert_eq!("low", fake.active_input());
+
+        vmix.toggle_recording().await.unwrap();
+        assert!(vmix.is_recording().await.unwrap());
+
+        let stats = vmix.stats().await.unwrap();
+        assert_eq!(Some(true), stats.recording);
+
+        assert_eq!(
+            vec!["CutDirect Input=low", "StartStopRecording"],
+            fake.received_functions()
+        );
+    }
+}
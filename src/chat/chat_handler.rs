@@ -8,7 +8,7 @@ use tokio::time;
 use tracing::{debug, error, info};
 
 use crate::chat::{self, HandleMessage};
-use crate::{config, error, events, switcher, user_manager, Noalbs};
+use crate::{config, error, events, state, switcher, user_manager, Noalbs};
 
 pub struct ChatHandler {
     chat_handler_rx: mpsc::Receiver<super::HandleMessage>,
@@ -171,7 +171,7 @@ impl ChatHandler {
 
         use switcher::SwitchType::*;
         match ss.switch_type {
-            Normal | Low => {
+            Normal | Low | Tier(_) => {
                 let bitrate = bitrate_msg(&user, lang).await;
                 msg += &format!(" | {}", bitrate);
             }
@@ -473,24 +473,15 @@ impl DispatchCommand {
             chat::Command::Start => self.start().await,
             chat::Command::Stop => self.stop().await,
             chat::Command::Switch => self.switch(params.next()).await,
-            chat::Command::Trigger => {
-                self.trigger(switcher::TriggerType::Low, params.next())
-                    .await
-            }
-            chat::Command::Otrigger => {
-                self.trigger(switcher::TriggerType::Offline, params.next())
-                    .await
-            }
-            chat::Command::Rtrigger => {
-                self.trigger(switcher::TriggerType::Rtt, params.next())
-                    .await
-            }
+            chat::Command::Trigger => self.trigger(switcher::TriggerType::Low, params).await,
+            chat::Command::Otrigger => self.trigger(switcher::TriggerType::Offline, params).await,
+            chat::Command::Rtrigger => self.trigger(switcher::TriggerType::Rtt, params).await,
             chat::Command::Version => self.version().await,
             chat::Command::PrivacyScene => self.privacy_scene().await,
             chat::Command::StartingScene => self.starting_scene().await,
             chat::Command::EndingScene => self.ending_scene().await,
             chat::Command::LiveScene => self.live_scene().await,
-            chat::Command::Obsinfo => {}
+            chat::Command::Obsinfo => self.obs_info().await,
             chat::Command::Mod => self.enable_mod(params.next()).await,
             chat::Command::Public => self.enable_public(params.next()).await,
             chat::Command::Sourceinfo => self.source_info(params.next()).await,
@@ -736,20 +727,62 @@ impl DispatchCommand {
         self.send(msg).await;
     }
 
-    async fn trigger(&self, kind: switcher::TriggerType, value_string: Option<&str>) {
+    async fn trigger<'a, I>(&self, kind: switcher::TriggerType, args: I)
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        // The trigger of a single stream server (ex; !trigger 2000 server=SRT)
+        let (servers, args): (Vec<&str>, Vec<&str>) =
+            args.into_iter().partition(|a| a.starts_with("server="));
+        let server_name = servers.last().and_then(|a| a.strip_prefix("server="));
+
+        if let Some(name) = server_name {
+            if !self.user.has_stream_server(name).await {
+                let msg = t!("trigger.errorServer", locale = &self.lang, name = name);
+                self.send(msg).await;
+                return;
+            }
+        }
+
+        let mut args = args.into_iter().peekable();
+
+        // Other triggers by name (ex; !trigger loss 5)
+        let kind = match args
+            .peek()
+            .and_then(|a| switcher::TriggerType::from_name(a))
+        {
+            Some(kind) => {
+                args.next();
+                kind
+            }
+            None => kind,
+        };
+
+        let mut value_string = args.next();
+
+        // The trigger used to leave the state again
+        let kind = if value_string == Some("recover") {
+            value_string = args.next();
+            kind.recover()
+        } else {
+            kind
+        };
+
         let value = match value_string {
+            Some("reset") => {
+                // Reset takes no value so the server name can follow it
+                self.reset_trigger(kind, server_name.or_else(|| args.next()))
+                    .await;
+                return;
+            }
             Some(name) => name,
             None => {
-                let msg = match &self.user.get_trigger_by_type(kind).await {
-                    Some(bitrate) => t!(
-                        "trigger.current",
-                        locale = &self.lang,
-                        number = &bitrate.to_string()
-                    ),
+                let msg = match self.user.get_trigger_by_type(kind, server_name).await {
+                    Some(value) => self.trigger_current(kind, value),
                     None => t!("trigger.disabled", locale = &self.lang),
                 };
 
-                self.send(msg).await;
+                self.send(with_server_name(server_name, msg)).await;
                 return;
             }
         };
@@ -767,12 +800,8 @@ impl DispatchCommand {
             }
         };
 
-        let msg = match &self.user.update_trigger(kind, value).await {
-            Some(value) => t!(
-                "trigger.success",
-                locale = &self.lang,
-                number = &value.to_string()
-            ),
+        let msg = match self.user.update_trigger(kind, server_name, value).await {
+            Some(value) => self.trigger_success(kind, value),
             None => t!(
                 "trigger.successDisabled",
                 locale = &self.lang,
@@ -781,7 +810,80 @@ impl DispatchCommand {
         };
 
         self.save_config().await;
-        self.send(msg).await;
+        self.send(with_server_name(server_name, msg)).await;
+    }
+
+    /// Removes the override of a stream server so the global trigger is used
+    /// again
+    async fn reset_trigger(&self, kind: switcher::TriggerType, server_name: Option<&str>) {
+        let server_name = match server_name {
+            Some(name) => name,
+            None => {
+                let msg = t!("trigger.errorReset", locale = &self.lang);
+                self.send(msg).await;
+                return;
+            }
+        };
+
+        if !self.user.has_stream_server(server_name).await {
+            let msg = t!(
+                "trigger.errorServer",
+                locale = &self.lang,
+                name = server_name
+            );
+            self.send(msg).await;
+            return;
+        }
+
+        self.user.reset_trigger(kind, server_name).await;
+        self.save_config().await;
+
+        let msg = t!("trigger.reset", locale = &self.lang);
+        self.send(with_server_name(Some(server_name), msg)).await;
+    }
+
+    /// Message with the current value in the unit of the trigger
+    fn trigger_current(&self, kind: switcher::TriggerType, value: u32) -> String {
+        let number = &value.to_string();
+
+        match kind {
+            switcher::TriggerType::Loss | switcher::TriggerType::LossRecover => {
+                t!("trigger.currentLoss", locale = &self.lang, number = number)
+            }
+            switcher::TriggerType::Dropped | switcher::TriggerType::DroppedRecover => {
+                t!(
+                    "trigger.currentDropped",
+                    locale = &self.lang,
+                    number = number
+                )
+            }
+            switcher::TriggerType::Links | switcher::TriggerType::LinksRecover => {
+                t!("trigger.currentLinks", locale = &self.lang, number = number)
+            }
+            _ => t!("trigger.current", locale = &self.lang, number = number),
+        }
+    }
+
+    /// Message with the new value in the unit of the trigger
+    fn trigger_success(&self, kind: switcher::TriggerType, value: u32) -> String {
+        let number = &value.to_string();
+
+        match kind {
+            switcher::TriggerType::Loss | switcher::TriggerType::LossRecover => {
+                t!("trigger.successLoss", locale = &self.lang, number = number)
+            }
+            switcher::TriggerType::Dropped | switcher::TriggerType::DroppedRecover => {
+                t!(
+                    "trigger.successDropped",
+                    locale = &self.lang,
+                    number = number
+                )
+            }
+            switcher::TriggerType::Links | switcher::TriggerType::LinksRecover => {
+                t!("trigger.successLinks", locale = &self.lang, number = number)
+            }
+            _ => t!("trigger.success", locale = &self.lang, number = number),
+        }
     }
 
     async fn notify(&self, enabled: Option<&str>) {
@@ -932,6 +1034,22 @@ impl DispatchCommand {
                 }
             }
             "retry" => self.set_retry_attempts(args.next()).await,
+            "dwell" => self.set_dwell(args.next(), args.next()).await,
+            "profile" => self.profile(args.next()).await,
+            "dryrun" => {
+                let enabled = match args.next().map(enabled_to_bool) {
+                    Some(Ok(enabled)) => enabled,
+                    _ => !self.user.get_dry_run().await,
+                };
+
+                self.user.set_dry_run(enabled).await;
+                self.save_config().await;
+                t!(
+                    "noalbs.dryRun",
+                    locale = &self.lang,
+                    condition = &condition_to_text(enabled, &self.lang)
+                )
+            }
             _ => String::new(),
         };
 
@@ -971,6 +1089,76 @@ impl DispatchCommand {
         )
     }
 
+    async fn profile(&self, name: Option<&str>) -> String {
+        let name = match name {
+            Some(name) => name,
+            None => {
+                return match self.user.get_active_profile().await {
+                    Some(name) => t!("noalbs.profile", locale = &self.lang, name = &name),
+                    None => t!("noalbs.profileNone", locale = &self.lang),
+                };
+            }
+        };
+
+        if !self.user.apply_profile(name).await {
+            return t!("noalbs.profileError", locale = &self.lang, name = name);
+        }
+
+        self.save_config().await;
+
+        t!("noalbs.profileSuccess", locale = &self.lang, name = name)
+    }
+
+    async fn set_dwell(&self, scene: Option<&str>, value_string: Option<&str>) -> String {
+        // Tiers can be set one by one by their name, tier sets every tier
+        let (scene, key) = match scene {
+            Some(scene @ ("live" | "normal")) => (scene, "normal"),
+            Some(scene @ ("low" | "offline" | "tier")) => (scene, scene),
+            Some(name) if self.user.has_tier(name).await => (name, name),
+            _ => return t!("noalbs.dwellErrorScene", locale = &self.lang),
+        };
+
+        let value = match value_string {
+            Some(value) => value,
+            None => {
+                return match self.user.get_dwell(key).await {
+                    Some(seconds) => t!(
+                        "noalbs.dwell",
+                        locale = &self.lang,
+                        scene = scene,
+                        seconds = &seconds.to_string()
+                    ),
+                    None => t!("noalbs.dwellDisabled", locale = &self.lang, scene = scene),
+                };
+            }
+        };
+
+        let value = match value.parse::<u32>() {
+            Ok(v) => v,
+            Err(_) => {
+                return t!("noalbs.dwellError", locale = &self.lang, seconds = value);
+            }
+        };
+
+        let msg = match self.user.set_dwell(key, value).await {
+            Some(seconds) => t!(
+                "noalbs.dwellSuccess",
+                locale = &self.lang,
+                scene = scene,
+                seconds = &seconds.to_string()
+            ),
+            None => t!(
+                "noalbs.dwellSuccessDisabled",
+                locale = &self.lang,
+                scene = scene
+            ),
+        };
+
+        self.save_config().await;
+
+        msg
+    }
+
     // TODO: Refactor these functions
     async fn privacy_scene(&self) {
         let state = self.user.state.read().await;
@@ -1011,7 +1199,7 @@ impl DispatchCommand {
     // TODO: Actually switch to the right scene
     async fn live_scene(&self) {
         let state = self.user.state.read().await;
-        let scene = &state.config.switcher.switching_scenes.normal;
+        let scene = &state.config.switcher.switching_scenes().normal;
 
         self.send(t!("scene.success", locale = &self.lang, scene = "live"))
             .await;
@@ -1021,6 +1209,7 @@ impl DispatchCommand {
     async fn source_info(&self, server_name: Option<&str>) {
         let state = &self.user.state.read().await;
         let stream_servers = &state.config.switcher.stream_servers;
+        let snapshot = &state.switcher_state.snapshot;
 
         let no_info = t!("sourceinfo.noInfo", locale = &self.lang);
 
@@ -1035,8 +1224,8 @@ impl DispatchCommand {
                 }
             };
 
-            let info = match server.stream_server.source_info().await {
-                Some(i) => i,
+            let info = match snapshot.status(name).and_then(|s| s.stats.as_ref()) {
+                Some(stats) => stats.source_info(&self.lang),
                 None => no_info,
             };
             self.send(format!("{}: {}", name, info)).await;
@@ -1047,10 +1236,10 @@ impl DispatchCommand {
         let mut msg = Vec::new();
 
         for s in stream_servers {
-            let info = s.stream_server.source_info().await;
+            let stats = snapshot.status(&s.name).and_then(|s| s.stats.as_ref());
 
-            if let Some(info) = info {
-                msg.push(format!("{}: {}", s.name, info));
+            if let Some(stats) = stats {
+                msg.push(format!("{}: {}", s.name, stats.source_info(&self.lang)));
             }
         }
 
@@ -1063,6 +1252,75 @@ impl DispatchCommand {
         self.send(msg.join(" - ")).await;
     }
 
+    async fn obs_info(&self) {
+        let (statuses, connection) = {
+            let lock = self.user.state.read().await;
+            let bs = &lock.broadcasting_software;
+
+            (bs.connection_statuses(), bs.connected())
+        };
+
+        // Not holding the state lock while waiting for the software
+        let stats = match connection {
+            Some(bsc) => Some(bsc.stats().await),
+            None => None,
+        };
+
+        let status_msg = |connection: &state::ConnectionStatus| match connection.status {
+            state::ClientStatus::Connected => t!(
+                "obsinfo.connected",
+                locale = &self.lang,
+                scene = &connection.current_scene
+            ),
+            state::ClientStatus::Disconnected => t!("obsinfo.disconnected", locale = &self.lang),
+        };
+
+        let info = match stats {
+            Some(Ok(stats)) => stats.obs_info(&self.lang),
+            Some(Err(_)) => t!("obsinfo.error", locale = &self.lang),
+            None => String::new(),
+        };
+
+        let with_info = |msg: String| {
+            if info.is_empty() {
+                msg
+            } else {
+                format!("{} | {}", msg, info)
+            }
+        };
+
+        if let [connection] = statuses.as_slice() {
+            self.send(with_info(status_msg(connection))).await;
+            return;
+        }
+
+        let msg: Vec<String> = statuses
+            .iter()
+            .map(|connection| {
+                let role = match connection.role {
+                    state::ConnectionRole::Primary => t!("obsinfo.primary", locale = &self.lang),
+                    state::ConnectionRole::Mirror => t!("obsinfo.mirror", locale = &self.lang),
+                };
+
+                let msg = t!(
+                    "obsinfo.connection",
+                    locale = &self.lang,
+                    name = &connection.name,
+                    role = &role,
+                    status = &status_msg(connection)
+                );
+
+                if connection.active {
+                    t!("obsinfo.active", locale = &self.lang, connection = &msg)
+                } else {
+                    msg
+                }
+            })
+            .collect();
+
+        self.send(with_info(msg.join(" - "))).await;
+    }
+
     async fn enable_mod(&self, enabled: Option<&str>) {
         if let Some(enabled) = enabled {
             if let Ok(b) = enabled_to_bool(enabled) {
@@ -1131,21 +1389,33 @@ fn enabled_to_bool(enabled: &str) -> Result<bool, error::Error> {
     Err(error::Error::EnabledToBoolConversionError)
 }
 
+fn with_server_name(server_name: Option<&str>, msg: String) -> String {
+    match server_name {
+        Some(name) => format!("{}: {}", name, msg),
+        None => msg,
+    }
+}
+
 async fn bitrate_msg(user: &Noalbs, lang: &str) -> String {
     let mut msg = String::new();
 
     let state = &user.state.read().await;
     let servers = &state.config.switcher.stream_servers;
+    let snapshot = &state.switcher_state.snapshot;
 
     for (i, s) in servers.iter().enumerate() {
-        let t = s.stream_server.bitrate().await;
         let sep = if i == 0 || msg.is_empty() { "" } else { " - " };
 
-        if let Some(bitrate_message) = t.message {
+        let stats = snapshot
+            .status(&s.name)
+            .and_then(|status| status.stats.as_ref());
+
+        if let Some(stats) = stats {
             let locale = t!(
                 "bitrate.success",
+                locale = lang,
                 name = &s.name,
-                message = &bitrate_message
+                message = &stats.bitrate_message(lang)
             );
             msg += &format!("{}{}", sep, locale);
         }
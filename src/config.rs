@@ -3,6 +3,7 @@ use std::{
     io::{Seek, SeekFrom},
 };
 
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Weekday};
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
@@ -17,6 +18,12 @@ pub struct Config {
     pub user: User,
     pub switcher: Switcher,
     pub software: SoftwareConnection,
+
+    /// Extra broadcasting software that the scene switches get mirrored to,
+    /// one of them takes over when the main software disconnects
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<MirrorConnection>,
+
     pub chat: Option<Chat>,
     pub optional_scenes: OptionalScenes,
     pub optional_options: OptionalOptions,
@@ -51,14 +58,41 @@ pub struct Switcher {
     /// bitrate state
     pub retry_attempts: u8,
 
-    /// Triggers to switch to the low or offline scenes
+    /// Keep the current scene instead of switching to offline when the
+    /// stats page of a stream server is unreachable or times out
+    pub hold_scene_when_unreachable: bool,
+
+    /// Max time in ms to wait for all stream servers every poll, servers
+    /// that didn't respond in time count as unreachable
+    pub poll_deadline_ms: u64,
+
+    /// Only log and send events of the scenes the switcher would switch to
+    /// without actually switching
+    pub dry_run: bool,
+
+    /// Appends the stats of every poll while streaming to this JSON lines
+    /// file, which can be replayed with `noalbs replay`
+    pub record_stats: Option<std::path::PathBuf>,
+
+    /// Triggers to switch to the low or offline scenes, use
+    /// [`Switcher::triggers`] to get them with the active profile applied
     pub triggers: switcher::Triggers,
 
-    /// The default switching scenes
+    /// The default switching scenes, use [`Switcher::switching_scenes`] to
+    /// get them with the active profile applied
     pub switching_scenes: switcher::SwitchingScenes,
 
     /// Add multiple stream servers to watch before switching to low or offline
     pub stream_servers: Vec<stream_servers::StreamServer>,
+
+    /// Named sets of switcher settings that can be activated at once
+    pub profiles: Vec<Profile>,
+
+    /// Activates profiles on certain days and times
+    pub schedule: Option<Schedule>,
+
+    /// The last activated profile
+    pub active_profile: Option<String>,
 }
 
 impl Switcher {
@@ -81,6 +115,161 @@ impl Switcher {
         //    self.switcher_enabled_notifier.notify_waiters();
         //}
     }
+
+    /// Activates the profile, returns false when the profile doesn't exist.
+    ///
+    /// The triggers and scenes of the profile only apply on top of the
+    /// configured ones, so they don't end up in the saved config.
+    pub fn apply_profile(&mut self, name: &str) -> bool {
+        let profile = match self.profiles.iter().find(|p| p.name == name) {
+            Some(profile) => profile.to_owned(),
+            None => return false,
+        };
+
+        if let Some(enabled) = profile.bitrate_switcher_enabled {
+            self.bitrate_switcher_enabled = enabled;
+        }
+
+        if let Some(retry_attempts) = profile.retry_attempts {
+            self.retry_attempts = retry_attempts;
+        }
+
+        self.active_profile = Some(profile.name);
+
+        true
+    }
+
+    fn profile(&self) -> Option<&Profile> {
+        let name = self.active_profile.as_ref()?;
+
+        self.profiles.iter().find(|p| &p.name == name)
+    }
+
+    /// The triggers with the overrides of the active profile applied
+    pub fn triggers(&self) -> switcher::Triggers {
+        match self.profile().and_then(|p| p.triggers.as_ref()) {
+            Some(overrides) => self.triggers.with_overrides(overrides),
+            None => self.triggers.to_owned(),
+        }
+    }
+
+    /// The trigger in use by the stream server when a name is given,
+    /// otherwise the global trigger
+    pub fn trigger(&self, kind: switcher::TriggerType, server_name: Option<&str>) -> Option<u32> {
+        let triggers = self.triggers();
+
+        match self
+            .stream_servers
+            .iter()
+            .find(|s| Some(s.name.as_str()) == server_name)
+        {
+            Some(server) => server.triggers(&triggers).get(&kind),
+            None => triggers.get(&kind),
+        }
+    }
+
+    /// The switching scenes of the active profile or the default ones
+    pub fn switching_scenes(&self) -> &switcher::SwitchingScenes {
+        self.profile()
+            .and_then(|p| p.switching_scenes.as_ref())
+            .unwrap_or(&self.switching_scenes)
+    }
+}
+
+/// Switcher settings that replace the current ones when activated, settings
+/// that aren't set stay the same
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub name: String,
+    pub bitrate_switcher_enabled: Option<bool>,
+    pub retry_attempts: Option<u8>,
+
+    /// Replace the configured triggers field by field while active
+    pub triggers: Option<switcher::TriggerOverrides>,
+
+    /// Replace the configured switching scenes while active
+    pub switching_scenes: Option<switcher::SwitchingScenes>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    /// Timezone of the times in the entries (ex; Europe/Amsterdam )
+    pub timezone: chrono_tz::Tz,
+
+    pub entries: Vec<ScheduleEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleEntry {
+    /// Name of the profile to activate
+    pub profile: String,
+
+    /// Days the time range starts on, every day when empty
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+
+    /// Start of the time range (ex; 07:30 )
+    #[serde(with = "hour_minute")]
+    pub start: NaiveTime,
+
+    /// End of the time range, ranges that end before they start continue
+    /// on the next day
+    #[serde(with = "hour_minute")]
+    pub end: NaiveTime,
+}
+
+impl ScheduleEntry {
+    fn on_day(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            return self.on_day(day) && time >= self.start && time < self.end;
+        }
+
+        (self.on_day(day) && time >= self.start) || (self.on_day(day.pred()) && time < self.end)
+    }
+}
+
+impl Schedule {
+    /// The profile that is scheduled at the given time, the first matching
+    /// entry wins
+    pub fn profile_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<&str> {
+        let local = time.with_timezone(&self.timezone);
+        let (day, time) = (local.weekday(), local.time());
+
+        self.entries
+            .iter()
+            .find(|e| e.contains(day, time))
+            .map(|e| e.profile.as_str())
+    }
+}
+
+/// Serializes times as 07:30
+mod hour_minute {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    const FORMAT: &str = "%H:%M";
+
+    pub fn serialize<S>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&time.format(FORMAT).to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&text, FORMAT).map_err(serde::de::Error::custom)
+    }
 }
 
 impl Default for Switcher {
@@ -92,12 +281,15 @@ impl Default for Switcher {
             auto_switch_notification: true,
             triggers: switcher::Triggers::default(),
             stream_servers: Vec::new(),
-            switching_scenes: switcher::SwitchingScenes {
-                normal: "live".to_string(),
-                low: "low".to_string(),
-                offline: "offline".to_string(),
-            },
+            switching_scenes: switcher::SwitchingScenes::new("live", "low", "offline"),
             retry_attempts: MAX_LOW_RETRY,
+            hold_scene_when_unreachable: false,
+            poll_deadline_ms: 1000,
+            dry_run: false,
+            record_stats: None,
+            profiles: Vec::new(),
+            schedule: None,
+            active_profile: None,
         }
     }
 }
@@ -107,6 +299,27 @@ impl Default for Switcher {
 #[serde(tag = "type")]
 pub enum SoftwareConnection {
     Obs(ObsConfig),
+
+    /// OBS 28 and newer, uses the obs-websocket 5.x protocol
+    ObsV5(ObsConfig),
+
+    /// vMix over its TCP API, the inputs are used as scenes
+    Vmix(VmixConfig),
+
+    /// Streamlabs Desktop over its JSON-RPC websocket API
+    Slobs(SlobsConfig),
+
+    /// A local ffmpeg or GStreamer process, no GUI needed
+    Pipeline(PipelineConfig),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MirrorConnection {
+    /// Shown in the status of the connections
+    pub name: String,
+
+    #[serde(flatten)]
+    pub connection: SoftwareConnection,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -116,6 +329,68 @@ pub struct ObsConfig {
     pub port: u16,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlobsConfig {
+    pub host: String,
+
+    /// Port of the API, 59650 by default
+    #[serde(default = "default_slobs_port")]
+    pub port: u16,
+
+    /// API token from Settings > Remote Control
+    pub token: Option<String>,
+}
+
+fn default_slobs_port() -> u16 {
+    59650
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VmixConfig {
+    pub host: String,
+
+    /// Port of the TCP API, 8099 by default
+    #[serde(default = "default_vmix_port")]
+    pub port: u16,
+}
+
+fn default_vmix_port() -> u16 {
+    8099
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PipelineConfig {
+    /// Where the input of the current scene gets sent to
+    pub output: String,
+
+    /// Input of every scene, like the feed or a slate video
+    pub scenes: HashMap<String, String>,
+
+    /// Program with its arguments, `{input}` and `{output}` get replaced
+    #[serde(default = "default_pipeline_command")]
+    pub command: Vec<String>,
+}
+
+fn default_pipeline_command() -> Vec<String> {
+    [
+        "ffmpeg",
+        "-hide_banner",
+        "-loglevel",
+        "error",
+        "-re",
+        "-i",
+        "{input}",
+        "-c",
+        "copy",
+        "-f",
+        "flv",
+        "{output}",
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
 pub trait ConfigLogic: Send + Sync {
     fn load(&self) -> Result<Config, error::Error>;
     fn save(&self, config: &Config) -> Result<(), error::Error>;
@@ -321,16 +596,17 @@ impl From<ConfigOld> for Config {
                 triggers: switcher::Triggers {
                     low: Some(o.obs.low_bitrate_trigger),
                     rtt: o.obs.high_rtt_trigger,
-                    offline: None,
-                },
-                switching_scenes: switcher::SwitchingScenes {
-                    normal: o.obs.normal_scene,
-                    low: o.obs.low_bitrate_scene,
-                    offline: o.obs.offline_scene,
+                    ..Default::default()
                 },
+                switching_scenes: switcher::SwitchingScenes::new(
+                    o.obs.normal_scene,
+                    o.obs.low_bitrate_scene,
+                    o.obs.offline_scene,
+                ),
                 ..Default::default()
             },
             software,
+            mirrors: Vec::new(),
             chat: Some(Chat {
                 platform: chat::ChatPlatform::Twitch,
                 username: o.twitch_chat.channel,
@@ -431,11 +707,13 @@ impl From<RtmpOld> for stream_servers::StreamServer {
                     Box::new(stream_servers::belabox::Belabox {
                         stats_url,
                         publisher,
+                        srtla: None,
                     })
                 } else {
                     Box::new(stream_servers::sls::SrtLiveServer {
                         stats_url,
                         publisher,
+                        srtla: None,
                     })
                 }
             }
@@ -443,11 +721,15 @@ impl From<RtmpOld> for stream_servers::StreamServer {
         };
 
         Self {
-            stream_server,
+            stream_server: stream_server.into(),
             name,
             priority: Some(0),
             override_scenes: None,
             depends_on: None,
+            triggers: None,
+            http: Default::default(),
+            history: Default::default(),
+            counters: Default::default(),
         }
     }
 }
@@ -526,3 +808,66 @@ fn update_command(
         c.alias.as_mut().unwrap().push(alias);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profile_overrides_triggers() {
+        let mut switcher: Switcher = serde_json::from_str(
+            r#"{
+                "triggers": { "low": 800, "rtt": 2500, "offline": 200 },
+                "profiles": [{ "name": "train", "triggers": { "low": 1000 } }]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(switcher.apply_profile("train"));
+
+        let triggers = switcher.triggers();
+        assert_eq!(Some(1000), triggers.low);
+        assert_eq!(Some(2500), triggers.rtt);
+        assert_eq!(Some(200), triggers.offline);
+
+        // The configured triggers get saved without the profile
+        let saved = serde_json::to_value(&switcher).unwrap();
+        assert_eq!(800, saved["triggers"]["low"]);
+        assert_eq!(Some(800), switcher.triggers.low);
+    }
+
+    #[test]
+    fn scheduled_profiles() {
+        let json = r#"{
+            "timezone": "Europe/Amsterdam",
+            "entries": [
+                {
+                    "profile": "train",
+                    "days": ["Mon", "Tue", "Wed", "Thu", "Fri"],
+                    "start": "07:00",
+                    "end": "10:00"
+                },
+                {
+                    "profile": "desk",
+                    "start": "19:00",
+                    "end": "01:00"
+                }
+            ]
+        }"#;
+
+        let schedule: Schedule = serde_json::from_str(json).unwrap();
+        let at = |time: &str| {
+            let time = DateTime::parse_from_rfc3339(time).unwrap();
+            schedule.profile_at(&time)
+        };
+
+        // Monday 08:30 in Amsterdam
+        assert_eq!(Some("train"), at("2023-10-02T06:30:00Z"));
+
+        // Sunday 08:30 in Amsterdam
+        assert_eq!(None, at("2023-10-01T06:30:00Z"));
+
+        // Tuesday 00:30 in Amsterdam, started on monday evening
+        assert_eq!(Some("desk"), at("2023-10-02T22:30:00Z"));
+    }
+}
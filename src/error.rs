@@ -3,6 +3,9 @@ pub enum Error {
     #[error("Can't access stats page")]
     StatsPageNotAvailable,
 
+    #[error("Stats page didn't respond before the poll deadline")]
+    PollDeadline,
+
     #[error("Reqwest error {0}")]
     PageRequest(#[from] reqwest::Error),
 
@@ -12,9 +15,33 @@ pub enum Error {
     #[error("OBS error {0}")]
     ObsError(#[from] obws::Error),
 
+    #[error("OBS request error {0}")]
+    ObsRequest(String),
+
+    #[error("Unable to authenticate with OBS")]
+    ObsAuthFailed,
+
+    #[error("Streamlabs request error {0}")]
+    SlobsRequest(String),
+
+    #[error("Unable to authenticate with Streamlabs")]
+    SlobsAuthFailed,
+
+    #[error("vMix error {0}")]
+    Vmix(String),
+
+    #[error("Pipeline error {0}")]
+    Pipeline(String),
+
+    #[error("Websocket error {0}")]
+    Websocket(#[from] tokio_tungstenite::tungstenite::Error),
+
     #[error("SwitchType conversion not allowed")]
     SwitchTypeNotSupported,
 
+    #[error("Tier {0} doesn't exist")]
+    TierNotFound(usize),
+
     // #[error("Sql error {0}")]
     // SqlError(#[from] sqlx::error::Error),
 
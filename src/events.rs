@@ -1,12 +1,23 @@
 use serde::Serialize;
 
+use crate::switcher::SwitchType;
+
 /// All events that might be send
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "event", content = "data")]
 pub enum Event<'a> {
-    PrefixChanged { prefix: &'a str },
-    SceneSwitched { scene: &'a str },
+    PrefixChanged {
+        prefix: &'a str,
+    },
+    SceneSwitched {
+        scene: &'a str,
+    },
+    #[serde(rename_all = "camelCase")]
+    DryRunSwitch {
+        scene: &'a str,
+        switch_type: SwitchType,
+    },
 }
 
 #[cfg(test)]
@@ -23,4 +34,18 @@ mod tests {
         let expected = r#"{"event":"prefixChanged","data":{"prefix":"!"}}"#;
         assert_eq!(expected, json);
     }
+
+    #[test]
+    fn dry_run_event() {
+        let event = Event::DryRunSwitch {
+            scene: "low",
+            switch_type: SwitchType::Low,
+        };
+
+        let json = serde_json::to_string(&event).unwrap();
+        println!("{}", json);
+
+        let expected = r#"{"event":"dryRunSwitch","data":{"scene":"low","switchType":"low"}}"#;
+        assert_eq!(expected, json);
+    }
 }
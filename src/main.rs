@@ -4,7 +4,11 @@ use std::{env, sync::Arc};
 use anyhow::Result;
 use tokio::signal;
 
-use noalbs::{chat::ChatPlatform, config, Noalbs};
+use noalbs::{
+    chat::ChatPlatform,
+    config::{self, ConfigLogic},
+    recording, Noalbs,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -18,6 +22,12 @@ async fn main() -> Result<()> {
 
     tracing_subscriber::fmt::init();
 
+    let args: Vec<String> = env::args().collect();
+
+    if args.get(1).map(String::as_str) == Some("replay") {
+        return replay(&args[2..]).await;
+    }
+
     let user_manager = noalbs::user_manager::UserManager::new();
 
     // Used to send messages to the chat handler
@@ -101,6 +111,32 @@ where
     Ok(noalbs_users)
 }
 
+/// Replays a recording through the switcher and prints the scenes it
+/// switched to
+async fn replay(args: &[String]) -> Result<()> {
+    let path = match args.first() {
+        Some(path) => path,
+        None => anyhow::bail!("Usage: noalbs replay <recording> [config]"),
+    };
+
+    let config_path = args.get(1).map_or("config.json", String::as_str);
+    let config = config::File {
+        name: config_path.into(),
+    }
+    .load()?;
+
+    let ticks = recording::read(path)?;
+    println!("Replaying {} polls of {}", ticks.len(), path);
+
+    let timeline = recording::replay(config, &ticks).await?;
+
+    for switch in timeline {
+        println!("{}", switch);
+    }
+
+    Ok(())
+}
+
 async fn print_if_new_version() -> Result<(), noalbs::error::Error> {
     let url = "https://api.github.com/repos/715209/nginx-obs-automatic-low-bitrate-switching/releases/latest";
     let dlu = "https://github.com/715209/nginx-obs-automatic-low-bitrate-switching/releases/latest";
@@ -4,7 +4,7 @@ use tokio::sync::{mpsc, RwLock};
 use tracing::{debug, info};
 
 use crate::{
-    broadcasting_software::obs::Obs,
+    broadcasting_software::{self, mirrored::Mirrored},
     chat, config, error,
     state::{self, State},
     stream_servers,
@@ -42,21 +42,21 @@ impl Noalbs {
 
         state.set_all_switchable_scenes();
         state.broadcasting_software.prev_scene =
-            state.config.switcher.switching_scenes.normal.to_owned();
+            state.config.switcher.switching_scenes().normal.to_owned();
 
         let state = Arc::new(RwLock::new(state));
 
         {
             let mut w_state = state.write().await;
 
-            let connection = match w_state.config.software {
-                config::SoftwareConnection::Obs(ref obs_conf) => {
-                    Obs::new(obs_conf.clone(), state.clone())
-                }
+            let connection = if w_state.config.mirrors.is_empty() {
+                broadcasting_software::connect(&w_state.config.software, state.clone())
+            } else {
+                Box::new(Mirrored::new(&w_state, state.clone()))
             };
 
             // Do i need this option here?
-            w_state.broadcasting_software.connection = Some(Box::new(connection));
+            w_state.broadcasting_software.connection = Some(connection.into());
         }
 
         let mut user = Self {
@@ -187,28 +187,115 @@ impl Noalbs {
         Ok(false)
     }
 
-    pub async fn get_trigger_by_type(&self, kind: switcher::TriggerType) -> Option<u32> {
+    pub async fn has_stream_server(&self, name: &str) -> bool {
         let state = &self.state.read().await;
-        let triggers = &state.config.switcher.triggers;
 
-        match kind {
-            switcher::TriggerType::Low => triggers.low,
-            switcher::TriggerType::Rtt => triggers.rtt,
-            switcher::TriggerType::Offline => triggers.offline,
+        state
+            .config
+            .switcher
+            .stream_servers
+            .iter()
+            .any(|s| s.name == name)
+    }
+
+    /// Gets the trigger of the stream server when a name is given, otherwise
+    /// the global trigger
+    pub async fn get_trigger_by_type(
+        &self,
+        kind: switcher::TriggerType,
+        server_name: Option<&str>,
+    ) -> Option<u32> {
+        let state = &self.state.read().await;
+
+        state.config.switcher.trigger(kind, server_name)
+    }
+
+    /// Updates the trigger of the stream server when a name is given,
+    /// otherwise the global trigger
+    pub async fn update_trigger(
+        &self,
+        kind: switcher::TriggerType,
+        server_name: Option<&str>,
+        value: u32,
+    ) -> Option<u32> {
+        let mut state = self.state.write().await;
+        let switcher = &mut state.config.switcher;
+
+        match switcher
+            .stream_servers
+            .iter_mut()
+            .find(|s| Some(s.name.as_str()) == server_name)
+        {
+            Some(server) => server
+                .triggers
+                .get_or_insert_with(Default::default)
+                .set(&kind, value),
+            None => {
+                let real_value = if value == 0 { None } else { Some(value) };
+                switcher.triggers.set(&kind, real_value);
+            }
         }
+
+        // The active profile can still override the new value
+        switcher.trigger(kind, server_name)
     }
 
-    pub async fn update_trigger(&self, kind: switcher::TriggerType, value: u32) -> Option<u32> {
+    /// Removes the override of the stream server so it uses the global
+    /// trigger again
+    pub async fn reset_trigger(&self, kind: switcher::TriggerType, server_name: &str) {
         let mut state = self.state.write().await;
-        let triggers = &mut state.config.switcher.triggers;
 
-        let real_value = if value == 0 { None } else { Some(value) };
+        let server = state
+            .config
+            .switcher
+            .stream_servers
+            .iter_mut()
+            .find(|s| s.name == server_name);
 
-        match kind {
-            switcher::TriggerType::Low => triggers.low = real_value,
-            switcher::TriggerType::Rtt => triggers.rtt = real_value,
-            switcher::TriggerType::Offline => triggers.offline = real_value,
+        if let Some(server) = server {
+            if let Some(overrides) = &mut server.triggers {
+                overrides.clear(&kind);
+
+                if *overrides == Default::default() {
+                    server.triggers = None;
+                }
+            }
         }
+    }
+
+    pub async fn set_triggers(&self, update: switcher::TriggersUpdate) {
+        let mut state = self.state.write().await;
+
+        state.config.switcher.triggers.update(update);
+        state.set_all_switchable_scenes();
+    }
+
+    pub async fn has_tier(&self, name: &str) -> bool {
+        let state = &self.state.read().await;
+
+        state
+            .config
+            .switcher
+            .triggers
+            .tiers
+            .iter()
+            .any(|t| t.name == name)
+    }
+
+    /// Gets the dwell time by the scene name used in chat, see
+    /// [`switcher::Dwell::by_name`]
+    pub async fn get_dwell(&self, scene: &str) -> Option<u32> {
+        let state = &self.state.read().await;
+
+        state.config.switcher.triggers.dwell.by_name(scene)
+    }
+
+    pub async fn set_dwell(&self, scene: &str, seconds: u32) -> Option<u32> {
+        let mut state = self.state.write().await;
+        let dwell = &mut state.config.switcher.triggers.dwell;
+
+        let real_value = if seconds == 0 { None } else { Some(seconds) };
+        dwell.set_by_name(scene, real_value);
 
         real_value
     }
@@ -239,6 +326,44 @@ impl Noalbs {
         toggle
     }
 
+    pub async fn get_dry_run(&self) -> bool {
+        let state = self.state.read().await;
+
+        state.config.switcher.dry_run
+    }
+
+    pub async fn set_dry_run(&self, enabled: bool) {
+        let mut state = self.state.write().await;
+
+        state.config.switcher.dry_run = enabled;
+    }
+
+    /// Sets the stats of a mock stream server, returns false when there is
+    /// no mock stream server with that name
+    pub async fn set_mock_stats(
+        &self,
+        server_name: &str,
+        stats: Option<stream_servers::mock::MockStats>,
+    ) -> bool {
+        let state = self.state.read().await;
+
+        let mock = state
+            .config
+            .switcher
+            .stream_servers
+            .iter()
+            .filter(|s| s.name == server_name)
+            .find_map(|s| s.stream_server.mock());
+
+        match mock {
+            Some(mock) => {
+                mock.set_stats(stats);
+                true
+            }
+            None => false,
+        }
+    }
+
     pub async fn get_enable_mod(&self) -> Result<bool, error::Error> {
         let state = &self.state.read().await;
         let chat = &state.config.chat.as_ref().ok_or(error::Error::NoChat)?;
@@ -317,6 +442,18 @@ impl Noalbs {
         }
     }
 
+    pub async fn get_active_profile(&self) -> Option<String> {
+        let state = self.state.read().await;
+
+        state.config.switcher.active_profile.to_owned()
+    }
+
+    pub async fn apply_profile(&self, name: &str) -> bool {
+        let mut state = self.state.write().await;
+
+        state.apply_profile(name)
+    }
+
     pub async fn set_password(&self, password: String) {
         let mut state = self.state.write().await;
 
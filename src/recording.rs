@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, sync::RwLock, time::Instant};
+
+use crate::{
+    broadcasting_software::replay::Replay,
+    config, error,
+    state::{self, ClientStatus, State},
+    stream_servers::{self, stats},
+    switcher::{SwitchType, Switcher},
+};
+
+/// Stats of every stream server from one poll, a line of a recording
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tick {
+    /// Milliseconds since the unix epoch
+    pub time: u64,
+
+    /// Keyed by stream server name
+    pub servers: HashMap<String, Recorded>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Recorded {
+    Online(stats::StreamStats),
+    Offline,
+    Unreachable,
+}
+
+impl Recorded {
+    /// The stats like a poll would return them, None when the stats page
+    /// was unreachable
+    pub fn stats(&self) -> Option<Option<stats::StreamStats>> {
+        match self {
+            Recorded::Online(stats) => Some(Some(stats.to_owned())),
+            Recorded::Offline => Some(None),
+            Recorded::Unreachable => None,
+        }
+    }
+}
+
+impl Tick {
+    pub fn new(snapshot: &stream_servers::Snapshot) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let servers = snapshot
+            .servers
+            .iter()
+            .map(|(name, status)| {
+                let recorded = match status {
+                    Some(stream_servers::Status {
+                        stats: Some(stats), ..
+                    }) => Recorded::Online(stats.to_owned()),
+                    Some(_) => Recorded::Offline,
+                    None => Recorded::Unreachable,
+                };
+
+                (name.to_owned(), recorded)
+            })
+            .collect();
+
+        Self { time, servers }
+    }
+}
+
+/// Appends the tick as a line to the recording
+pub async fn append<P>(path: P, tick: &Tick) -> Result<(), error::Error>
+where
+    P: AsRef<Path>,
+{
+    let mut line = serde_json::to_vec(tick)?;
+    line.push(b'\n');
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+
+    file.write_all(&line).await?;
+    file.flush().await?;
+
+    Ok(())
+}
+
+pub fn read<P>(path: P) -> Result<Vec<Tick>, error::Error>
+where
+    P: AsRef<Path>,
+{
+    let file = std::fs::File::open(path)?;
+    let mut ticks = Vec::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        ticks.push(serde_json::from_str(&line)?);
+    }
+
+    Ok(ticks)
+}
+
+/// A scene switch made while replaying a recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct Switch {
+    /// Time since the start of the recording
+    pub offset: Duration,
+    pub scene: String,
+}
+
+impl fmt::Display for Switch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}",
+            stats::format_duration(self.offset.as_secs()),
+            self.scene
+        )
+    }
+}
+
+/// Switcher connected to a fake broadcasting software that is streaming in
+/// the offline scene. Nothing runs in the background, every call to switch
+/// makes a single decision on the current snapshot
+pub struct Simulation {
+    pub switcher: Switcher,
+    pub software: Replay,
+    prev_switch_type: SwitchType,
+    same_type: u8,
+    last_switch: Option<(SwitchType, Instant)>,
+}
+
+impl Simulation {
+    pub fn new(mut config: config::Config) -> Self {
+        config.switcher.bitrate_switcher_enabled = true;
+        config.switcher.dry_run = false;
+        config.switcher.record_stats = None;
+
+        let software = Replay::default();
+
+        let mut state = State {
+            config,
+            switcher_state: state::SwitcherState::default(),
+            broadcasting_software: state::BroadcastingSoftwareState::default(),
+            event_senders: Vec::new(),
+        };
+
+        state.set_all_switchable_scenes();
+
+        let scenes = state.config.switcher.switching_scenes();
+        state.broadcasting_software.prev_scene = scenes.normal.to_owned();
+        state.broadcasting_software.current_scene = scenes.offline.to_owned();
+        state.broadcasting_software.status = ClientStatus::Connected;
+        state.broadcasting_software.is_streaming = true;
+        state.broadcasting_software.connection = Some(std::sync::Arc::new(software.clone()));
+
+        // Chat notifications get dropped
+        let (chat_sender, _) = tokio::sync::mpsc::channel(1);
+
+        let switcher = Switcher {
+            state: std::sync::Arc::new(RwLock::new(state)),
+            chat_sender,
+        };
+
+        Self {
+            switcher,
+            software,
+            prev_switch_type: SwitchType::Offline,
+            same_type: 0,
+            last_switch: None,
+        }
+    }
+
+    /// Runs the switcher on the current snapshot at the time `now`, returns
+    /// the scene when it switched
+    pub async fn switch(&mut self, now: Instant) -> Result<Option<String>, error::Error> {
+        if self
+            .switcher
+            .get_sleep_notifier_if_necessary()
+            .await
+            .is_some()
+        {
+            return Ok(None);
+        }
+
+        self.switcher
+            .switch(
+                &mut self.prev_switch_type,
+                &mut self.same_type,
+                &mut self.last_switch,
+                now,
+            )
+            .await?;
+
+        let scene = self.software.take_switched();
+
+        if let Some(scene) = &scene {
+            // The event OBS would send after switching
+            self.switcher
+                .state
+                .write()
+                .await
+                .broadcasting_software
+                .current_scene = scene.to_owned();
+        }
+
+        Ok(scene)
+    }
+}
+
+/// Feeds the recording through the switcher as if the stream happened
+/// again and returns the scenes it switched to.
+///
+/// Every tick gets evaluated at its recorded time, so the dwell times behave
+/// the same as during the stream no matter how fast it replays
+pub async fn replay(config: config::Config, ticks: &[Tick]) -> Result<Vec<Switch>, error::Error> {
+    let mut simulation = Simulation::new(config);
+
+    let start = match ticks.first() {
+        Some(tick) => tick.time,
+        None => return Ok(Vec::new()),
+    };
+
+    let started = Instant::now();
+    let mut last_time = start;
+    let mut timeline = Vec::new();
+
+    for tick in ticks {
+        // The clock never goes back, even when the recording does
+        last_time = last_time.max(tick.time);
+        let now = started + Duration::from_millis(last_time - start);
+
+        {
+            let mut state = simulation.switcher.state.write().await;
+            let polled = tick
+                .servers
+                .iter()
+                .map(|(name, r)| (name.to_owned(), r.stats()));
+            state.switcher_state.snapshot = Switcher::evaluate_polls(&state, polled, now);
+        }
+
+        if let Some(scene) = simulation.switch(now).await? {
+            timeline.push(Switch {
+                offset: Duration::from_millis(tick.time.saturating_sub(start)),
+                scene,
+            });
+        }
+    }
+
+    Ok(timeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "user": { "name": "test" },
+        "switcher": {
+            "retryAttempts": 2,
+            "triggers": { "low": 800 },
+            "streamServers": [
+                {
+                    "streamServer": {
+                        "type": "GenericJson",
+                        "statsUrl": "http://localhost:8181/stats",
+                        "bitrate": "/bitrate"
+                    },
+                    "name": "srt",
+                    "priority": 0
+                }
+            ]
+        },
+        "software": { "type": "Obs", "host": "localhost", "port": 4444 },
+        "optionalScenes": {},
+        "optionalOptions": {}
+    }"#;
+
+    fn tick(second: u64, recorded: Recorded) -> Tick {
+        Tick {
+            time: 1_700_000_000_000 + second * 1000,
+            servers: HashMap::from([("srt".to_string(), recorded)]),
+        }
+    }
+
+    fn online(bitrate: u64) -> Recorded {
+        Recorded::Online(stats::StreamStats {
+            bitrate,
+            ..Default::default()
+        })
+    }
+
+    fn switch(second: u64, scene: &str) -> Switch {
+        Switch {
+            offset: Duration::from_secs(second),
+            scene: scene.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn replay_recording() {
+        let config: config::Config = serde_json::from_str(CONFIG).unwrap();
+
+        let bitrates = [5000, 5000, 300, 300, 300, 300, 300];
+        let mut ticks: Vec<Tick> = bitrates
+            .iter()
+            .enumerate()
+            .map(|(second, &bitrate)| tick(second as u64, online(bitrate)))
+            .collect();
+
+        ticks.extend((7..10).map(|second| tick(second, Recorded::Offline)));
+        ticks.push(tick(10, online(5000)));
+
+        let line = serde_json::to_string(&ticks[7]).unwrap();
+        assert_eq!(
+            r#"{"time":1700000007000,"servers":{"srt":"offline"}}"#,
+            line
+        );
+
+        let timeline = replay(config, &ticks).await.unwrap();
+
+        assert_eq!(
+            vec![
+                switch(0, "live"),
+                switch(4, "low"),
+                switch(9, "offline"),
+                switch(10, "live"),
+            ],
+            timeline
+        );
+        assert_eq!("0:00:04 low", timeline[1].to_string());
+    }
+}
@@ -3,7 +3,7 @@ use std::{collections::HashSet, sync::Arc};
 use serde::Serialize;
 use tokio::sync::{mpsc, Notify};
 
-use crate::{broadcasting_software::BroadcastingSoftwareLogic, config};
+use crate::{broadcasting_software::BroadcastingSoftwareLogic, config, stream_servers};
 
 pub struct State {
     pub config: config::Config,
@@ -17,16 +17,21 @@ impl State {
     pub fn set_all_switchable_scenes(&mut self) {
         let all_scenes = &mut self.switcher_state.switchable_scenes;
 
-        let scenes = &self.config.switcher.switching_scenes;
+        let scenes = self.config.switcher.switching_scenes();
         all_scenes.insert(scenes.low.to_owned());
         all_scenes.insert(scenes.normal.to_owned());
         all_scenes.insert(scenes.offline.to_owned());
 
+        for tier in &self.config.switcher.triggers.tiers {
+            all_scenes.insert(tier.scene.to_owned());
+        }
+
         for servers in &self.config.switcher.stream_servers {
             if let Some(scenes) = &servers.override_scenes {
                 all_scenes.insert(scenes.low.to_owned());
                 all_scenes.insert(scenes.normal.to_owned());
                 all_scenes.insert(scenes.offline.to_owned());
+                all_scenes.extend(scenes.tiers.values().cloned());
             }
 
             if let Some(depends_on) = &servers.depends_on {
@@ -34,9 +39,36 @@ impl State {
                 all_scenes.insert(scenes.low.to_owned());
                 all_scenes.insert(scenes.normal.to_owned());
                 all_scenes.insert(scenes.offline.to_owned());
+                all_scenes.extend(scenes.tiers.values().cloned());
             }
         }
     }
+
+    pub fn send_event<T>(&self, message: &T)
+    where
+        T: Serialize,
+    {
+        for sender in &self.event_senders {
+            sender.send(message);
+        }
+    }
+
+    /// Activates the profile, returns false when it doesn't exist
+    pub fn apply_profile(&mut self, name: &str) -> bool {
+        if !self.config.switcher.apply_profile(name) {
+            return false;
+        }
+
+        self.set_all_switchable_scenes();
+
+        if self.config.switcher.bitrate_switcher_enabled {
+            self.switcher_state
+                .switcher_enabled_notifier()
+                .notify_waiters();
+        }
+
+        true
+    }
 }
 
 pub struct SwitcherState {
@@ -45,7 +77,11 @@ pub struct SwitcherState {
     /// All switchable scenes
     pub switchable_scenes: HashSet<String>,
 
+    /// Status of the stream servers from the last poll
+    pub snapshot: stream_servers::Snapshot,
+
     switcher_enabled_notifier: Arc<Notify>,
+    snapshot_notifier: Arc<Notify>,
 }
 
 impl SwitcherState {
@@ -53,6 +89,11 @@ impl SwitcherState {
         self.switcher_enabled_notifier.clone()
     }
 
+    /// Notified after every poll of the stream servers
+    pub fn snapshot_notifier(&self) -> Arc<Notify> {
+        self.snapshot_notifier.clone()
+    }
+
     pub async fn wait_till_enabled(&self) {
         self.switcher_enabled_notifier().notified().await;
     }
@@ -64,6 +105,8 @@ impl Default for SwitcherState {
             last_used_server: None,
             switcher_enabled_notifier: Arc::new(Notify::new()),
             switchable_scenes: HashSet::new(),
+            snapshot: stream_servers::Snapshot::default(),
+            snapshot_notifier: Arc::new(Notify::new()),
         }
     }
 }
@@ -76,7 +119,10 @@ pub struct BroadcastingSoftwareState {
     pub last_stream_started_at: std::time::Instant,
 
     // TODO?
-    pub connection: Option<Box<dyn BroadcastingSoftwareLogic>>,
+    pub connection: Option<Arc<dyn BroadcastingSoftwareLogic>>,
+
+    /// Status of every connection when there are mirrors, empty otherwise
+    pub connections: Vec<ConnectionStatus>,
 
     connected_notifier: Arc<Notify>,
     start_streaming_notifier: Arc<Notify>,
@@ -95,6 +141,32 @@ impl BroadcastingSoftwareState {
     pub fn switch_scene_notifier(&self) -> Arc<Notify> {
         self.switch_scene_notifier.clone()
     }
+
+    /// Status of every connection, the single connection is the primary
+    /// when there are no mirrors
+    pub fn connection_statuses(&self) -> Vec<ConnectionStatus> {
+        if !self.connections.is_empty() {
+            return self.connections.to_owned();
+        }
+
+        vec![ConnectionStatus {
+            name: "primary".to_string(),
+            role: ConnectionRole::Primary,
+            status: self.status,
+            current_scene: self.current_scene.to_owned(),
+            is_streaming: self.is_streaming,
+            active: true,
+        }]
+    }
+
+    /// The connection while it's connected, can be used after releasing the
+    /// state lock so slow requests don't block the other tasks
+    pub fn connected(&self) -> Option<Arc<dyn BroadcastingSoftwareLogic>> {
+        match &self.connection {
+            Some(connection) if self.status == ClientStatus::Connected => Some(connection.clone()),
+            _ => None,
+        }
+    }
 }
 
 impl std::fmt::Debug for BroadcastingSoftwareState {
@@ -117,6 +189,7 @@ impl Default for BroadcastingSoftwareState {
             status: ClientStatus::Disconnected,
             is_streaming: false,
             connection: None,
+            connections: Vec::new(),
             connected_notifier: Arc::new(Notify::new()),
             start_streaming_notifier: Arc::new(Notify::new()),
             switch_scene_notifier: Arc::new(Notify::new()),
@@ -125,12 +198,34 @@ impl Default for BroadcastingSoftwareState {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum ClientStatus {
     Connected,
     Disconnected,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionRole {
+    /// The software from the `software` section
+    Primary,
+    Mirror,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatus {
+    pub name: String,
+    pub role: ConnectionRole,
+    pub status: ClientStatus,
+    pub current_scene: String,
+    pub is_streaming: bool,
+
+    /// The state of the user follows this connection
+    pub active: bool,
+}
+
 #[derive(Debug)]
 pub struct BroadcastClient {
     /// Unique token for the current client
@@ -3,8 +3,8 @@ use log::{error, trace};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::{Bsl, StreamServersCommands, SwitchLogic};
-use crate::switcher::{SwitchType, Triggers};
+use super::{http::HttpClient, srtla, stats::StreamStats, Bsl, SwitchLogic};
+use crate::{error, switcher::SwitchType};
 
 #[derive(Deserialize, Debug)]
 pub struct Stat {
@@ -21,25 +21,25 @@ pub struct Belabox {
 
     /// StreamID of the where you are publishing the feed. (ex; publish/live/feed1 )
     pub publisher: String,
+
+    /// Optional SRTLA receiver when bonding multiple connections
+    pub srtla: Option<srtla::Srtla>,
 }
 
 impl Belabox {
-    pub async fn get_stats(&self) -> Option<Stat> {
-        let res = match reqwest::get(&self.stats_url).await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let res = client.send(client.get(&self.stats_url)).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
-        let data: Value = serde_json::from_str(&text).ok()?;
+        let text = res.text().await?;
+        let data: Value = match serde_json::from_str(&text) {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
         let publisher = &data["publishers"][&self.publisher];
 
         let stream: Stat = match serde_json::from_value(publisher.to_owned()) {
@@ -47,81 +47,53 @@ impl Belabox {
             Err(error) => {
                 trace!("{}", &data);
                 error!("Error parsing stats ({}) {}", self.stats_url, error);
-                return None;
+                return Ok(None);
             }
         };
 
         trace!("{:#?}", stream);
-        Some(stream)
+        Ok(Some(stream))
     }
 }
 
 #[async_trait]
 #[typetag::serde]
 impl SwitchLogic for Belabox {
-    /// Which scene to switch to
-    async fn switch(&self, triggers: &Triggers) -> SwitchType {
-        let stats = match self.get_stats().await {
-            Some(b) => b,
-            None => return SwitchType::Offline,
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
         };
 
-        if let Some(offline) = triggers.offline {
-            if stats.bitrate > 0 && stats.bitrate <= offline.into() {
-                return SwitchType::Offline;
-            }
-        }
-
-        if stats.bitrate == 0 {
-            return SwitchType::Offline;
-        }
-
-        if stats.bitrate == 1 {
-            return SwitchType::Previous;
-        }
-
-        if let Some(low) = triggers.low {
-            if stats.bitrate <= low.into() {
-                return SwitchType::Low;
-            }
-        }
+        // BELABOX reports a bitrate of 0 when nothing is being received
+        let bitrate = stats.bitrate.max(0) as u64;
 
-        if let Some(rtt) = triggers.rtt {
-            if stats.rtt >= rtt.into() {
-                return SwitchType::Low;
-            }
+        if bitrate == 0 {
+            return Ok(None);
         }
 
-        return SwitchType::Normal;
-    }
-}
-
-#[async_trait]
-#[typetag::serde]
-impl StreamServersCommands for Belabox {
-    async fn bitrate(&self) -> super::Bitrate {
-        let stats = match self.get_stats().await {
-            Some(stats) => stats,
-            None => return super::Bitrate { message: None },
+        // The SRTLA receiver is optional, the stream works without it
+        let links = match &self.srtla {
+            Some(srtla) => srtla.get_stats(client).await.ok().flatten(),
+            None => None,
         };
 
-        if stats.bitrate == 0 {
-            return super::Bitrate { message: None };
-        }
-
-        let message = format!("{}, {} ms", stats.bitrate, stats.rtt.round());
-        super::Bitrate {
-            message: Some(message),
-        }
+        Ok(Some(StreamStats {
+            bitrate,
+            rtt: Some(stats.rtt),
+            packets_dropped: Some(stats.dropped_pkts.max(0) as u64),
+            links: links.map(|l| l.links()),
+            ..Default::default()
+        }))
     }
 
-    async fn source_info(&self) -> Option<String> {
-        let stats = self.get_stats().await?;
-
-        let bitrate = format!("{} Kbps, {} ms", stats.bitrate, stats.rtt.round(),);
-        let dropped = format!("dropped {} packets", stats.dropped_pkts);
+    /// A bitrate of 1 means the stream just started
+    fn switch_override(&self, stats: &StreamStats) -> Option<SwitchType> {
+        if stats.bitrate == 1 {
+            return Some(SwitchType::Previous);
+        }
 
-        Some(format!("{} | {}", bitrate, dropped))
+        None
     }
 }
 
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
+
+#[derive(Debug)]
+pub struct Stat {
+    /// Bitrate in Kbps after applying the multiplier
+    pub bitrate: u64,
+    pub rtt: Option<f64>,
+    pub dropped: Option<u64>,
+    pub is_live: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GenericJson {
+    /// URL to the stats page that responds with JSON
+    pub stats_url: String,
+
+    /// Extra headers to send with the request
+    pub headers: Option<HashMap<String, String>>,
+
+    /// JSON pointer to the bitrate (ex; /publishers/live/bitrate )
+    pub bitrate: String,
+
+    /// JSON pointer to the RTT in ms
+    pub rtt: Option<String>,
+
+    /// JSON pointer to the amount of dropped packets
+    pub dropped: Option<String>,
+
+    /// JSON pointer to a value that is true when the stream is live
+    pub is_live: Option<String>,
+
+    /// Multiply the bitrate with this to get Kbps (ex; 0.001 for bps)
+    pub multiplier: Option<f64>,
+}
+
+impl GenericJson {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let mut request = client.get(&self.stats_url);
+
+        if let Some(headers) = &self.headers {
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+        }
+
+        let res = client.send(request).await?;
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing stats page ({})", self.stats_url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+        let data: Value = match serde_json::from_str(&text) {
+            Ok(data) => data,
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", self.stats_url, error);
+                return Ok(None);
+            }
+        };
+
+        let stream = self.parse(&data);
+
+        trace!("{:#?}", stream);
+        Ok(stream)
+    }
+
+    fn parse(&self, data: &Value) -> Option<Stat> {
+        let bitrate = data.pointer(&self.bitrate).and_then(as_f64)?;
+        let bitrate = (bitrate * self.multiplier.unwrap_or(1.0)).round() as u64;
+
+        let rtt = self
+            .rtt
+            .as_ref()
+            .and_then(|p| data.pointer(p))
+            .and_then(as_f64);
+
+        let dropped = self
+            .dropped
+            .as_ref()
+            .and_then(|p| data.pointer(p))
+            .and_then(as_f64)
+            .map(|d| d as u64);
+
+        let is_live = match &self.is_live {
+            Some(pointer) => data.pointer(pointer).map_or(false, as_bool),
+            None => true,
+        };
+
+        Some(Stat {
+            bitrate,
+            rtt,
+            dropped,
+            is_live,
+        })
+    }
+}
+
+/// Numbers are sometimes send as strings
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_bool(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().map_or(false, |n| n != 0.0),
+        Value::String(s) => s == "true" || s == "1",
+        _ => false,
+    }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for GenericJson {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        if !stats.is_live {
+            return Ok(None);
+        }
+
+        Ok(Some(StreamStats {
+            bitrate: stats.bitrate,
+            rtt: stats.rtt,
+            packets_dropped: stats.dropped,
+            ..Default::default()
+        }))
+    }
+}
+
+#[typetag::serde]
+impl Bsl for GenericJson {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generic() -> GenericJson {
+        GenericJson {
+            stats_url: "http://localhost/stats".to_string(),
+            headers: None,
+            bitrate: "/ingest/0/bps".to_string(),
+            rtt: Some("/ingest/0/link/rtt".to_string()),
+            dropped: Some("/ingest/0/dropped".to_string()),
+            is_live: Some("/ingest/0/state".to_string()),
+            multiplier: Some(0.001),
+        }
+    }
+
+    #[test]
+    fn parse_with_pointers() {
+        let data: Value = serde_json::from_str(
+            r#"{
+                "ingest": [
+                    {
+                        "bps": 4520000,
+                        "link": { "rtt": "38.5" },
+                        "dropped": 12,
+                        "state": true
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let stats = generic().parse(&data).unwrap();
+        println!("{:#?}", stats);
+
+        assert_eq!(4520, stats.bitrate);
+        assert_eq!(Some(38.5), stats.rtt);
+        assert_eq!(Some(12), stats.dropped);
+        assert!(stats.is_live);
+    }
+
+    #[test]
+    fn missing_bitrate() {
+        let data: Value = serde_json::from_str(r#"{ "ingest": [] }"#).unwrap();
+
+        assert!(generic().parse(&data).is_none());
+    }
+}
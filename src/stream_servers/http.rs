@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{error, warn};
+
+use crate::error::Error;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpConfig {
+    /// Max time in ms to connect to the stats page
+    pub connect_timeout_ms: u64,
+
+    /// Max time in ms for the whole request
+    pub timeout_ms: u64,
+
+    /// Amount of times to retry when the stats page is unreachable
+    pub retries: u8,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 1000,
+            timeout_ms: 2000,
+            retries: 0,
+        }
+    }
+}
+
+/// Connection pooled client used to poll the stats page of a stream server.
+///
+/// Gets (de)serialized as its [`HttpConfig`].
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    config: HttpConfig,
+}
+
+impl HttpClient {
+    pub fn new(config: HttpConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()
+            .expect("Unable to create HTTP client");
+
+        Self { client, config }
+    }
+
+    pub fn get<U: reqwest::IntoUrl>(&self, url: U) -> reqwest::RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends the request, retrying when the stats page is unreachable.
+    ///
+    /// An error means the stats page couldn't be reached, any response
+    /// including non successful status codes will be returned.
+    pub async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0;
+
+        loop {
+            // Requests without a body can always be cloned
+            let req = request.try_clone().expect("Request with a streaming body");
+
+            let error = match req.send().await {
+                Ok(res) => return Ok(res),
+                Err(e) => e,
+            };
+
+            if attempt >= self.config.retries {
+                if error.is_timeout() {
+                    warn!("Stats page timed out: {}", error);
+                } else {
+                    error!("Stats page is unreachable: {}", error);
+                }
+
+                return Err(Error::PageRequest(error));
+            }
+
+            attempt += 1;
+            warn!("Retrying stats page [{}]: {}", attempt, error);
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(HttpConfig::default())
+    }
+}
+
+impl Serialize for HttpClient {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.config.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for HttpClient {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let config = HttpConfig::deserialize(deserializer)?;
+
+        Ok(Self::new(config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::Filter;
+
+    #[tokio::test]
+    async fn slow_stats_page_times_out() {
+        let route = warp::path("stats").and_then(|| async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            Ok::<_, warp::Rejection>("{}")
+        });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let client = HttpClient::new(HttpConfig {
+            timeout_ms: 100,
+            retries: 1,
+            ..Default::default()
+        });
+
+        let url = format!("http://{}/stats", addr);
+        let error = client.send(client.get(&url)).await.unwrap_err();
+
+        match error {
+            Error::PageRequest(e) => assert!(e.is_timeout()),
+            e => panic!("Expected a request error, got {}", e),
+        }
+    }
+}
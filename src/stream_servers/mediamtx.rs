@@ -0,0 +1,334 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaMtxPath {
+    pub name: String,
+    pub source: Option<Source>,
+    pub ready: bool,
+    pub tracks: Vec<String>,
+    pub bytes_received: u64,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Source {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ConnList<T> {
+    items: Vec<T>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SrtConn {
+    pub id: String,
+    pub remote_addr: String,
+    #[serde(rename = "msRTT")]
+    pub ms_rtt: f64,
+    pub packets_received: u64,
+    pub packets_received_loss: u64,
+    pub packets_received_drop: u64,
+    pub mbps_receive_rate: f64,
+    pub ms_receive_buf: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RtmpConn {
+    pub id: String,
+    pub remote_addr: String,
+}
+
+#[derive(Debug)]
+pub enum Connection {
+    Srt(SrtConn),
+    Rtmp(RtmpConn),
+}
+
+#[derive(Debug)]
+pub struct Stat {
+    pub path: MediaMtxPath,
+    pub connection: Option<Connection>,
+
+    /// Calculated from the bytes received since the last poll
+    pub bitrate: u32,
+}
+
+impl Stat {
+    pub fn rtt(&self) -> Option<f64> {
+        match &self.connection {
+            Some(Connection::Srt(srt)) => Some(srt.ms_rtt),
+            _ => None,
+        }
+    }
+}
+
+/// Bytes received at the last poll
+#[derive(Debug)]
+struct Sample {
+    time: Instant,
+    bytes_received: u64,
+    bitrate: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaMtx {
+    /// URL to the MediaMTX control API (ex; http://127.0.0.1:9997 )
+    pub stats_url: String,
+
+    /// Name of the path you are publishing to (ex; live/feed1 )
+    pub path: String,
+
+    #[serde(skip)]
+    last_sample: Mutex<Option<Sample>>,
+}
+
+impl MediaMtx {
+    /// Polls that are closer together than this reuse the last bitrate
+    const MIN_SAMPLE_MS: u128 = 500;
+
+    pub fn new(stats_url: String, path: String) -> Self {
+        Self {
+            stats_url,
+            path,
+            last_sample: Mutex::new(None),
+        }
+    }
+
+    async fn get_json<T>(&self, client: &HttpClient, url: &str) -> Result<Option<T>, error::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let res = client.send(client.get(url)).await?;
+
+        // MediaMTX responds with not found when nobody is publishing
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing stats page ({})", url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+
+        match serde_json::from_str(&text) {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", url, error);
+                Ok(None)
+            }
+        }
+    }
+
+    async fn get_connection(
+        &self,
+        client: &HttpClient,
+        source: &Source,
+    ) -> Result<Option<Connection>, error::Error> {
+        let connection = match source.kind.as_ref() {
+            "srtConn" => {
+                let url = format!("{}/v3/srtconns/list", &self.stats_url);
+                let list: Option<ConnList<SrtConn>> = self.get_json(client, &url).await?;
+
+                list.and_then(|l| l.items.into_iter().find(|c| c.id == source.id))
+                    .map(Connection::Srt)
+            }
+            "rtmpConn" => {
+                let url = format!("{}/v3/rtmpconns/list", &self.stats_url);
+                let list: Option<ConnList<RtmpConn>> = self.get_json(client, &url).await?;
+
+                list.and_then(|l| l.items.into_iter().find(|c| c.id == source.id))
+                    .map(Connection::Rtmp)
+            }
+            _ => None,
+        };
+
+        Ok(connection)
+    }
+
+    /// Bitrate in Kbps based on the difference between the last poll
+    fn calculate_bitrate(&self, bytes_received: u64) -> u32 {
+        let mut last_sample = self.last_sample.lock().unwrap();
+        let now = Instant::now();
+
+        let bitrate = match &*last_sample {
+            Some(last) if bytes_received >= last.bytes_received => {
+                let elapsed = now.duration_since(last.time).as_millis();
+
+                if elapsed < Self::MIN_SAMPLE_MS {
+                    return last.bitrate;
+                }
+
+                let bits = (bytes_received - last.bytes_received) * 8;
+                (bits as u128 * 1000 / elapsed / 1024) as u32
+            }
+            // First poll or the path got recreated
+            _ => 0,
+        };
+
+        *last_sample = Some(Sample {
+            time: now,
+            bytes_received,
+            bitrate,
+        });
+
+        bitrate
+    }
+
+    fn reset_bitrate(&self) {
+        *self.last_sample.lock().unwrap() = None;
+    }
+
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let url = format!("{}/v3/paths/get/{}", &self.stats_url, &self.path);
+
+        let path: MediaMtxPath = match self.get_json(client, &url).await? {
+            Some(path) => path,
+            None => {
+                self.reset_bitrate();
+                return Ok(None);
+            }
+        };
+
+        let source = match (&path.source, path.ready) {
+            (Some(source), true) => source,
+            _ => {
+                self.reset_bitrate();
+                return Ok(None);
+            }
+        };
+
+        let connection = self.get_connection(client, source).await?;
+        let bitrate = self.calculate_bitrate(path.bytes_received);
+
+        let stat = Stat {
+            path,
+            connection,
+            bitrate,
+        };
+
+        trace!("{:#?}", stat);
+        Ok(Some(stat))
+    }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for MediaMtx {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        let mut stream_stats = StreamStats {
+            bitrate: stats.bitrate.into(),
+            ..Default::default()
+        };
+
+        if let Some(Connection::Srt(srt)) = &stats.connection {
+            stream_stats.rtt = Some(srt.ms_rtt);
+            stream_stats.packets_received = Some(srt.packets_received);
+            stream_stats.packets_lost = Some(srt.packets_received_loss);
+            stream_stats.packets_dropped = Some(srt.packets_received_drop);
+        }
+
+        Ok(Some(stream_stats))
+    }
+}
+
+#[typetag::serde]
+impl Bsl for MediaMtx {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_with_srt_source() {
+        let text = r#"
+            {
+                "name": "live/feed1",
+                "confName": "all_others",
+                "source": {
+                    "type": "srtConn",
+                    "id": "f47ac10b-58cc-4372-a567-0e02b2c3d479"
+                },
+                "ready": true,
+                "readyTime": "2023-10-02T12:03:41.239711356Z",
+                "tracks": ["H264", "MPEG-4 Audio"],
+                "bytesReceived": 43827104,
+                "bytesSent": 0,
+                "readers": []
+            }
+        "#;
+
+        let parsed: MediaMtxPath = serde_json::from_str(text).unwrap();
+        println!("{:#?}", parsed);
+
+        assert_eq!("srtConn", parsed.source.unwrap().kind);
+    }
+
+    #[test]
+    fn srt_conn_list() {
+        let text = r#"
+            {
+                "pageCount": 1,
+                "itemCount": 1,
+                "items": [
+                    {
+                        "id": "f47ac10b-58cc-4372-a567-0e02b2c3d479",
+                        "created": "2023-10-02T12:03:40.908201219Z",
+                        "remoteAddr": "192.168.1.20:51234",
+                        "state": "publish",
+                        "path": "live/feed1",
+                        "query": "",
+                        "packetsSent": 0,
+                        "packetsReceived": 298217,
+                        "packetsReceivedLoss": 12,
+                        "packetsReceivedDrop": 2,
+                        "bytesReceived": 43827104,
+                        "msRTT": 48.2,
+                        "mbpsReceiveRate": 5.82,
+                        "msReceiveBuf": 240
+                    }
+                ]
+            }
+        "#;
+
+        let parsed: ConnList<SrtConn> = serde_json::from_str(text).unwrap();
+        println!("{:#?}", parsed);
+
+        assert_eq!(12, parsed.items[0].packets_received_loss);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bitrate_from_bytes_received() {
+        let mediamtx = MediaMtx::new("http://localhost:9997".to_string(), "live".to_string());
+
+        assert_eq!(0, mediamtx.calculate_bitrate(1_000_000));
+
+        // Too close to the last poll, the bitrate stays the same
+        tokio::time::advance(std::time::Duration::from_millis(100)).await;
+        assert_eq!(0, mediamtx.calculate_bitrate(1_000_000 + 1024));
+
+        tokio::time::advance(std::time::Duration::from_millis(900)).await;
+        assert_eq!(2048, mediamtx.calculate_bitrate(1_000_000 + 256 * 1024));
+    }
+}
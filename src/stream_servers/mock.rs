@@ -0,0 +1,195 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
+
+/// Stream server that doesn't poll anything, it reports scripted stats or
+/// the stats set through the websocket API. Used in tests and to try out
+/// triggers without streaming
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MockServer {
+    /// Stats that get reported one after another, starting at the first
+    /// poll. The last step stays when not repeating
+    #[serde(default)]
+    pub script: Vec<MockStep>,
+
+    /// Start again from the first step after the last one
+    #[serde(default)]
+    pub repeat: bool,
+
+    /// Stats that replace the script until cleared
+    #[serde(skip)]
+    live: Mutex<Option<MockStats>>,
+
+    #[serde(skip)]
+    started: Mutex<Option<Instant>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MockStep {
+    /// How long to report the stats
+    pub seconds: u64,
+
+    #[serde(flatten)]
+    pub stats: MockStats,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MockStats {
+    /// Bitrate in Kbps, the stream is offline when not set
+    pub bitrate: Option<u64>,
+
+    /// RTT in ms
+    pub rtt: Option<f64>,
+
+    /// Fail like a stats page that can't be reached
+    pub unreachable: bool,
+}
+
+impl MockStep {
+    pub fn online(seconds: u64, bitrate: u64) -> Self {
+        Self {
+            seconds,
+            stats: MockStats {
+                bitrate: Some(bitrate),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn offline(seconds: u64) -> Self {
+        Self {
+            seconds,
+            stats: MockStats::default(),
+        }
+    }
+
+    pub fn unreachable(seconds: u64) -> Self {
+        Self {
+            seconds,
+            stats: MockStats {
+                unreachable: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl MockServer {
+    pub fn scripted(script: Vec<MockStep>) -> Self {
+        Self {
+            script,
+            ..Default::default()
+        }
+    }
+
+    /// Reports these stats instead of the script, None goes back to the
+    /// script
+    pub fn set_stats(&self, stats: Option<MockStats>) {
+        *self.live.lock().unwrap() = stats;
+    }
+
+    /// The stats to report now, None when there is nothing to report
+    fn current(&self) -> Option<MockStats> {
+        if let Some(stats) = self.live.lock().unwrap().as_ref() {
+            return Some(stats.to_owned());
+        }
+
+        let started = *self
+            .started
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        let mut elapsed = started.elapsed().as_secs();
+
+        let total: u64 = self.script.iter().map(|step| step.seconds).sum();
+
+        if self.repeat && total > 0 {
+            elapsed %= total;
+        }
+
+        let mut end = 0;
+
+        for step in &self.script {
+            end += step.seconds;
+
+            if elapsed < end {
+                return Some(step.stats.to_owned());
+            }
+        }
+
+        self.script.last().map(|step| step.stats.to_owned())
+    }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for MockServer {
+    async fn stats(&self, _client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.current() {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        if stats.unreachable {
+            return Err(error::Error::StatsPageNotAvailable);
+        }
+
+        Ok(stats.bitrate.map(|bitrate| StreamStats {
+            bitrate,
+            rtt: stats.rtt,
+            ..Default::default()
+        }))
+    }
+
+    fn mock(&self) -> Option<&MockServer> {
+        Some(self)
+    }
+}
+
+#[typetag::serde]
+impl Bsl for MockServer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn scripted_stats() {
+        let mock = MockServer::scripted(vec![
+            MockStep::online(2, 5000),
+            MockStep::offline(1),
+            MockStep::unreachable(1),
+        ]);
+
+        let client = HttpClient::default();
+        let second = std::time::Duration::from_secs(1);
+        let bitrate = |stats: Option<StreamStats>| stats.map(|s| s.bitrate);
+
+        assert_eq!(Some(5000), bitrate(mock.stats(&client).await.unwrap()));
+
+        tokio::time::advance(second * 2).await;
+        assert_eq!(None, bitrate(mock.stats(&client).await.unwrap()));
+
+        mock.set_stats(Some(MockStats {
+            bitrate: Some(300),
+            ..Default::default()
+        }));
+        assert_eq!(Some(300), bitrate(mock.stats(&client).await.unwrap()));
+
+        mock.set_stats(None);
+        tokio::time::advance(second).await;
+        assert!(mock.stats(&client).await.is_err());
+
+        // The last step stays
+        tokio::time::advance(second * 10).await;
+        assert!(mock.stats(&client).await.is_err());
+    }
+}
@@ -1,45 +1,107 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
-use crate::switcher;
+use crate::{error, switcher};
 
 pub mod belabox;
+pub mod generic;
+pub mod http;
+pub mod mediamtx;
+pub mod mock;
 pub mod nginx;
 pub mod nimble;
 pub mod nms;
+pub mod ome;
+pub mod prometheus;
 pub mod sls;
+pub mod smoothing;
+pub mod srs;
+pub mod srtla;
+pub mod stats;
 
 #[async_trait]
 #[typetag::serde(tag = "type")]
 pub trait SwitchLogic {
-    /// Which scene to switch to
-    async fn switch(&self, triggers: &switcher::Triggers) -> switcher::SwitchType;
+    /// Polls the stats page once, None when the stream is offline.
+    /// Errors when the stats page is unreachable
+    async fn stats(
+        &self,
+        client: &http::HttpClient,
+    ) -> Result<Option<stats::StreamStats>, error::Error>;
+
+    /// Stream server specific states that decide the scene before the
+    /// triggers get checked. A bitrate of 0 usually means the stream just
+    /// started
+    fn switch_override(&self, stats: &stats::StreamStats) -> Option<switcher::SwitchType> {
+        if stats.bitrate == 0 {
+            return Some(switcher::SwitchType::Previous);
+        }
+
+        None
+    }
+
+    /// Used to set the stats of a mock stream server through the websocket
+    /// API
+    fn mock(&self) -> Option<&mock::MockServer> {
+        None
+    }
 }
 
-/// Chat commands
-#[async_trait]
 #[typetag::serde(tag = "type")]
-pub trait StreamServersCommands {
-    async fn bitrate(&self) -> Bitrate;
-    async fn source_info(&self) -> Option<String>;
+pub trait Bsl: SwitchLogic + Send + Sync {}
+
+/// Result of polling a stream server
+#[derive(Debug, Clone)]
+pub struct Status {
+    /// Which scene to switch to
+    pub switch_type: switcher::SwitchType,
+
+    /// None when the stream is offline
+    pub stats: Option<stats::StreamStats>,
 }
 
-#[typetag::serde(tag = "type")]
-pub trait Bsl: SwitchLogic + StreamServersCommands + Send + Sync {}
+impl Status {
+    pub fn offline() -> Self {
+        Self {
+            switch_type: switcher::SwitchType::Offline,
+            stats: None,
+        }
+    }
+}
+
+/// Status of every stream server from the last poll
+#[derive(Debug, Default, Clone)]
+pub struct Snapshot {
+    /// Keyed by stream server name, None when the stats page was unreachable
+    pub servers: HashMap<String, Option<Status>>,
+}
+
+impl Snapshot {
+    /// The last status of the stream server, None when it was unreachable
+    /// or didn't get polled yet
+    pub fn status(&self, name: &str) -> Option<&Status> {
+        self.servers.get(name)?.as_ref()
+    }
 
-#[derive(Debug)]
-pub struct Bitrate {
-    // pub name: &'a str,
-    pub message: Option<String>,
-    // pub priority: i32,
+    pub fn is_unreachable(&self, name: &str) -> bool {
+        matches!(self.servers.get(name), Some(None))
+    }
 }
 
 // TODO: This needs a better name
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StreamServer {
-    /// The stream server
-    pub stream_server: Box<dyn Bsl>,
+    /// The stream server, shared so it can be polled without holding the
+    /// state lock
+    pub stream_server: Arc<dyn Bsl>,
 
     /// A name to differentiate in case of multiple stream servers
     pub name: String,
@@ -51,6 +113,91 @@ pub struct StreamServer {
     pub override_scenes: Option<switcher::SwitchingScenes>,
 
     pub depends_on: Option<DependsOn>,
+
+    /// Override the global triggers
+    pub triggers: Option<switcher::TriggerOverrides>,
+
+    /// Timeouts and retries used when polling the stats page
+    #[serde(default)]
+    pub http: http::HttpClient,
+
+    /// Bitrate of the last polls used to smooth the triggers
+    #[serde(skip)]
+    pub history: Mutex<smoothing::History>,
+
+    /// Packet counters of the last poll used for the loss and drop rates
+    #[serde(skip)]
+    pub counters: Mutex<stats::PacketCounters>,
+}
+
+impl StreamServer {
+    /// The global triggers with the overrides of this stream server applied
+    pub fn triggers(&self, global: &switcher::Triggers) -> switcher::Triggers {
+        match &self.triggers {
+            Some(overrides) => global.with_overrides(overrides),
+            None => global.to_owned(),
+        }
+    }
+
+    /// Polls the stats page, gives up when the deadline is reached. Doesn't
+    /// borrow the stream server so the state can be unlocked while waiting
+    pub fn poll(
+        &self,
+        deadline: Duration,
+    ) -> impl Future<Output = Result<Option<stats::StreamStats>, error::Error>> + Send + 'static
+    {
+        let stream_server = self.stream_server.clone();
+        let http = self.http.clone();
+
+        async move {
+            match tokio::time::timeout(deadline, stream_server.stats(&http)).await {
+                Ok(stats) => stats,
+                Err(_) => Err(error::Error::PollDeadline),
+            }
+        }
+    }
+
+    /// Decides the switch type from the stats polled at `now`, also used
+    /// when replaying a recording
+    pub fn evaluate(
+        &self,
+        stats: Option<stats::StreamStats>,
+        triggers: &switcher::Triggers,
+        prev: Option<switcher::SwitchType>,
+        now: tokio::time::Instant,
+    ) -> Status {
+        let mut stats = match stats {
+            Some(stats) => stats,
+            None => {
+                self.history.lock().unwrap().clear();
+                self.counters.lock().unwrap().clear();
+                return Status::offline();
+            }
+        };
+
+        self.counters.lock().unwrap().update(&mut stats, now);
+
+        let triggers = self.triggers(triggers);
+
+        let switch_type = match self.stream_server.switch_override(&stats) {
+            Some(switch_type) => switch_type,
+            None => {
+                let mut smoothed = stats.clone();
+                smoothed.bitrate = self
+                    .history
+                    .lock()
+                    .unwrap()
+                    .push(stats.bitrate, &triggers.smoothing);
+
+                triggers.switch_type(&smoothed, prev)
+            }
+        };
+
+        Status {
+            switch_type,
+            stats: Some(stats),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
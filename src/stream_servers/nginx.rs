@@ -2,8 +2,12 @@ use async_trait::async_trait;
 use log::{error, trace};
 use serde::{Deserialize, Serialize};
 
-use super::{Bsl, StreamServersCommands, SwitchLogic};
-use crate::switcher::{SwitchType, Triggers};
+use super::{
+    http::HttpClient,
+    stats::{Resolution, StreamStats},
+    Bsl, SwitchLogic,
+};
+use crate::error;
 
 #[derive(Deserialize, Debug)]
 struct NginxRtmpStats {
@@ -29,6 +33,8 @@ struct NginxRtmpLive {
 #[derive(Deserialize, Debug)]
 pub struct NginxRtmpStream {
     pub name: String,
+    /// Time in ms since the stream started
+    pub time: Option<u64>,
     pub bw_video: u32,
     pub meta: Option<Meta>,
     pub active: Option<()>,
@@ -76,28 +82,24 @@ pub struct Nginx {
 impl Nginx {
     /// 0 bitrate means the stream just started.
     /// the stats update every 10 seconds.
-    pub async fn get_stats(&self) -> Option<NginxRtmpStream> {
-        //TODO: keep the reqwest object around for future requests
-        let res = match reqwest::get(&self.stats_url).await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+    pub async fn get_stats(
+        &self,
+        client: &HttpClient,
+    ) -> Result<Option<NginxRtmpStream>, error::Error> {
+        let res = client.send(client.get(&self.stats_url)).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
+        let text = res.text().await?;
         let parsed: NginxRtmpStats = match quick_xml::de::from_str(&text) {
             Ok(stats) => stats,
             Err(error) => {
                 trace!("{}", &text);
                 error!("Error parsing stats ({}) {}", self.stats_url, error);
-                return None;
+                return Ok(None);
             }
         };
 
@@ -118,87 +120,43 @@ impl Nginx {
             .pop();
 
         trace!("{:#?}", filter);
-        filter
+        Ok(filter)
     }
 }
 
 #[async_trait]
 #[typetag::serde]
 impl SwitchLogic for Nginx {
-    /// Which scene to switch to
-    async fn switch(&self, triggers: &Triggers) -> SwitchType {
-        let stats = match self.get_stats().await {
-            Some(b) => b,
-            None => return SwitchType::Offline,
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
         };
 
-        let bitrate = stats.bw_video / 1024;
-
         if stats.active.is_none() {
-            return SwitchType::Offline;
-        }
-
-        if let Some(offline) = triggers.offline {
-            if bitrate > 0 && bitrate <= offline {
-                return SwitchType::Offline;
-            }
-        }
-
-        if bitrate == 0 {
-            return SwitchType::Previous;
-        }
-
-        if let Some(low) = triggers.low {
-            if bitrate <= low {
-                return SwitchType::Low;
-            }
+            return Ok(None);
         }
 
-        return SwitchType::Normal;
-    }
-}
-
-#[async_trait]
-#[typetag::serde]
-impl StreamServersCommands for Nginx {
-    async fn bitrate(&self) -> super::Bitrate {
-        let stats = match self.get_stats().await {
-            Some(stats) => stats,
-            None => return super::Bitrate { message: None },
+        let mut stream_stats = StreamStats {
+            bitrate: (stats.bw_video / 1024).into(),
+            uptime: stats.time.map(|ms| ms / 1000),
+            ..Default::default()
         };
 
-        let bitrate = stats.bw_video / 1024;
-        super::Bitrate {
-            message: Some(format!("{}", bitrate)),
+        if let Some(meta) = stats.meta {
+            stream_stats.resolution = Some(Resolution {
+                width: meta.video.width,
+                height: meta.video.height,
+            });
+            stream_stats.fps = Some(meta.video.frame_rate);
+            stream_stats.video_codec = Some(meta.video.codec);
+            stream_stats.audio_codec = meta.audio.codec;
+            stream_stats.audio_profile = meta.audio.profile;
+            stream_stats.audio_sample_rate = meta.audio.sample_rate;
+            stream_stats.audio_channels = meta.audio.channels;
         }
-    }
-
-    async fn source_info(&self) -> Option<String> {
-        let stats = self.get_stats().await?;
-        let meta = stats.meta?;
-        let video = meta.video;
-        let audio = meta.audio;
-
-        let bitrate = format!("{} Kbps", stats.bw_video / 1024);
-
-        let v_info = format!(
-            "{}p{} | {} {} {}",
-            video.height,
-            video.frame_rate,
-            video.codec,
-            video.profile.unwrap_or_default(),
-            video.level.unwrap_or_default()
-        );
-
-        let a_info = format!(
-            "{} {} {}Hz, {} channels",
-            audio.codec.unwrap_or_default(),
-            audio.profile.unwrap_or_default(),
-            audio.sample_rate.unwrap_or_default(),
-            audio.channels.unwrap_or_default(),
-        );
 
-        Some(format!("{} | {} | {}", bitrate, v_info, a_info))
+        Ok(Some(stream_stats))
     }
 }
 
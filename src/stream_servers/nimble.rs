@@ -2,8 +2,12 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-use super::{Bsl, StreamServersCommands, SwitchLogic};
-use crate::switcher::{SwitchType, Triggers};
+use super::{
+    http::HttpClient,
+    stats::{Resolution, StreamStats},
+    Bsl, SwitchLogic,
+};
+use crate::{error, switcher::SwitchType};
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "PascalCase")]
@@ -81,6 +85,13 @@ pub struct Stat {
     pub rtmp: Streams,
 }
 
+impl Stat {
+    /// Bitrate in Kbps, None when the bandwidth isn't a number
+    pub fn bitrate(&self) -> Option<u32> {
+        Some(self.rtmp.bandwidth.parse::<u32>().ok()? / 1024)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Nimble {
@@ -98,124 +109,107 @@ pub struct Nimble {
 }
 
 impl Nimble {
-    pub async fn get_stats(&self) -> Option<Stat> {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
         let url = format!("{}/manage/srt_receiver_stats", &self.stats_url);
 
-        let res = match reqwest::get(&url).await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+        let res = client.send(client.get(&url)).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
-        let srt_stats: NimbleSrtStats = serde_json::from_str(&text).ok()?;
+        let text = res.text().await?;
+        let srt_stats: NimbleSrtStats = match serde_json::from_str(&text) {
+            Ok(stats) => stats,
+            Err(_) => return Ok(None),
+        };
 
-        let srt_receiver = srt_stats
+        let srt_receiver = match srt_stats
             .srt_receivers
-            .iter()
-            .find(|x| x.id.contains(&self.id))?;
+            .into_iter()
+            .find(|x| x.id.contains(&self.id))
+        {
+            Some(receiver) => receiver,
+            None => return Ok(None),
+        };
 
         if srt_receiver.state == "disconnected" {
-            return None;
+            return Ok(None);
         }
 
         // RTMP status for bitrate. srt_receiver_stats seems to give an averaged number that isn't as useful.
         // Probably requires nimble to be configured to make the video from SRT available on RTMP even though it's not used anywhere
         let url = format!("{}/manage/rtmp_status", &self.stats_url);
 
-        let res = match reqwest::get(&url).await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+        let res = client.send(client.get(&url)).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
-        let rtmp_stats: Vec<NimbleRtmpStats> = serde_json::from_str(&text).ok()?;
-
-        let rtmp_app = rtmp_stats.iter().find(|x| x.app == self.application)?;
-        let rtmp_stream = rtmp_app.streams.iter().find(|x| x.strm == self.key)?;
-
-        let stat = Stat {
-            srt: srt_receiver.to_owned(),
-            rtmp: rtmp_stream.to_owned(),
+        let text = res.text().await?;
+        let rtmp_stats: Vec<NimbleRtmpStats> = match serde_json::from_str(&text) {
+            Ok(stats) => stats,
+            Err(_) => return Ok(None),
         };
 
-        Some(stat)
+        let rtmp_stream = rtmp_stats
+            .into_iter()
+            .filter(|x| x.app == self.application)
+            .flat_map(|x| x.streams)
+            .find(|x| x.strm == self.key);
+
+        Ok(rtmp_stream.map(|rtmp| Stat {
+            srt: srt_receiver,
+            rtmp,
+        }))
     }
 }
 
 #[async_trait]
 #[typetag::serde]
 impl SwitchLogic for Nimble {
-    async fn switch(&self, triggers: &Triggers) -> SwitchType {
-        let stats = match self.get_stats().await {
-            Some(b) => b,
-            None => return SwitchType::Offline,
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
         };
 
-        let bitrate = stats.rtmp.bandwidth.parse::<u32>().unwrap();
-        let bitrate = bitrate / 1024;
-
-        if let Some(offline) = triggers.offline {
-            if bitrate > 0 && bitrate <= offline {
-                return SwitchType::Offline;
-            }
-        }
-
-        if bitrate == 0 {
-            return SwitchType::Normal;
-        }
-
-        if let Some(low) = triggers.low {
-            if bitrate <= low {
-                return SwitchType::Low;
-            }
-        }
-
-        if let Some(rtt) = triggers.rtt {
-            if stats.srt.stats.link.rtt >= rtt.into() {
-                return SwitchType::Low;
+        let bitrate = match stats.bitrate() {
+            Some(bitrate) => bitrate,
+            None => {
+                error!(
+                    "Error parsing the bandwidth ({}) {:?}",
+                    self.stats_url, stats.rtmp.bandwidth
+                );
+                return Ok(None);
             }
-        }
-
-        return SwitchType::Normal;
-    }
-}
-
-#[async_trait]
-#[typetag::serde]
-impl StreamServersCommands for Nimble {
-    async fn bitrate(&self) -> super::Bitrate {
-        let stats = match self.get_stats().await {
-            Some(stats) => stats,
-            None => return super::Bitrate { message: None },
         };
 
-        let bitrate = stats.rtmp.bandwidth.parse::<u32>().unwrap();
-        let bitrate = bitrate / 1024;
+        let recv = &stats.srt.stats.recv;
+
+        Ok(Some(StreamStats {
+            bitrate: bitrate.into(),
+            rtt: Some(stats.srt.stats.link.rtt),
+            packets_received: Some(recv.packets_received),
+            packets_lost: Some(recv.packets_lost),
+            packets_dropped: Some(recv.packets_dropped),
+            resolution: Resolution::parse(&stats.rtmp.resolution),
+            video_codec: Some(stats.rtmp.vcodec.to_owned()),
+            audio_codec: stats.rtmp.acodec.to_owned(),
+            ..Default::default()
+        }))
+    }
 
-        let message = format!("{}, {} ms", bitrate, stats.srt.stats.link.rtt.round());
-        super::Bitrate {
-            message: Some(message),
+    /// Nimble reports a bitrate of 0 while the stream is starting
+    fn switch_override(&self, stats: &StreamStats) -> Option<SwitchType> {
+        if stats.bitrate == 0 {
+            return Some(SwitchType::Normal);
         }
-    }
 
-    async fn source_info(&self) -> Option<String> {
-        self.bitrate().await.message
+        None
     }
 }
 
@@ -2,14 +2,16 @@ use async_trait::async_trait;
 use log::{error, trace};
 use serde::{Deserialize, Serialize};
 
-use super::{Bsl, StreamServersCommands, SwitchLogic};
-use crate::switcher::{SwitchType, Triggers};
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Stat {
     pub is_live: bool,
     pub viewers: u64,
+
+    /// Seconds since the stream started
     pub duration: u64,
     pub bitrate: u64,
 }
@@ -36,93 +38,55 @@ pub struct NodeMediaServer {
 }
 
 impl NodeMediaServer {
-    pub async fn get_stats(&self) -> Option<Stat> {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
         let url = format!("{}/{}/{}", &self.stats_url, &self.application, &self.key);
 
-        let client = reqwest::Client::new();
         let mut request = client.get(url);
 
         if let Some(auth) = &self.auth {
             request = request.basic_auth(&auth.username, Some(&auth.password));
         }
 
-        let res = match request.send().await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+        let res = client.send(request).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
-        let stream: Stat = serde_json::from_str(&text).ok()?;
+        let text = res.text().await?;
+        let stream: Stat = match serde_json::from_str(&text) {
+            Ok(stream) => stream,
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", self.stats_url, error);
+                return Ok(None);
+            }
+        };
 
         trace!("{:#?}", stream);
-        Some(stream)
+        Ok(Some(stream))
     }
 }
 
 #[async_trait]
 #[typetag::serde]
 impl SwitchLogic for NodeMediaServer {
-    /// Which scene to switch to
-    async fn switch(&self, triggers: &Triggers) -> SwitchType {
-        let stats = match self.get_stats().await {
-            Some(b) => b,
-            None => return SwitchType::Offline,
-        };
-
-        if !stats.is_live {
-            return SwitchType::Offline;
-        }
-
-        if let Some(offline) = triggers.offline {
-            if stats.bitrate > 0 && stats.bitrate <= offline.into() {
-                return SwitchType::Offline;
-            }
-        }
-
-        if stats.bitrate == 0 {
-            return SwitchType::Previous;
-        }
-
-        if let Some(low) = triggers.low {
-            if stats.bitrate <= low.into() {
-                return SwitchType::Low;
-            }
-        }
-
-        return SwitchType::Normal;
-    }
-}
-
-#[async_trait]
-#[typetag::serde]
-impl StreamServersCommands for NodeMediaServer {
-    async fn bitrate(&self) -> super::Bitrate {
-        let stats = match self.get_stats().await {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
             Some(stats) => stats,
-            None => return super::Bitrate { message: None },
+            None => return Ok(None),
         };
 
         if !stats.is_live {
-            return super::Bitrate { message: None };
-        }
-
-        super::Bitrate {
-            message: Some(format!("{}", stats.bitrate)),
+            return Ok(None);
         }
-    }
-
-    async fn source_info(&self) -> Option<String> {
-        let stats = self.get_stats().await?;
 
-        Some(format!("{} Kbps", stats.bitrate))
+        Ok(Some(StreamStats {
+            bitrate: stats.bitrate,
+            uptime: Some(stats.duration),
+            ..Default::default()
+        }))
     }
 }
 
@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OmeResponse {
+    status_code: u16,
+    response: Option<Stat>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Stat {
+    /// Bits per second received during the last interval
+    #[serde(alias = "lastThroughputIn")]
+    pub last_recv_throughput_in: u64,
+    pub total_connections: u64,
+    pub created_time: String,
+}
+
+impl Stat {
+    pub fn bitrate(&self) -> u64 {
+        self.last_recv_throughput_in / 1024
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Auth {
+    /// Access token configured in the OME server config
+    access_token: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OvenMediaEngine {
+    /// URL to the OME REST API (ex; http://127.0.0.1:8081 )
+    pub stats_url: String,
+
+    /// Virtual host name
+    pub vhost: String,
+
+    /// Stream application
+    pub application: String,
+
+    /// Stream key
+    pub key: String,
+
+    pub auth: Option<Auth>,
+}
+
+impl OvenMediaEngine {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let url = format!(
+            "{}/v1/stats/current/vhosts/{}/apps/{}/streams/{}",
+            &self.stats_url, &self.vhost, &self.application, &self.key
+        );
+
+        let mut request = client.get(&url);
+
+        if let Some(auth) = &self.auth {
+            let token = base64::encode(&auth.access_token);
+            request = request.header(reqwest::header::AUTHORIZATION, format!("Basic {}", token));
+        }
+
+        let res = client.send(request).await?;
+
+        // OME responds with not found when the stream doesn't exist
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing stats page ({})", self.stats_url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+        let parsed: OmeResponse = match serde_json::from_str(&text) {
+            Ok(stats) => stats,
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", self.stats_url, error);
+                return Ok(None);
+            }
+        };
+
+        if parsed.status_code != 200 {
+            return Ok(None);
+        }
+
+        trace!("{:#?}", parsed.response);
+        Ok(parsed.response)
+    }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for OvenMediaEngine {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        Ok(Some(StreamStats {
+            bitrate: stats.bitrate(),
+            ..Default::default()
+        }))
+    }
+}
+
+#[typetag::serde]
+impl Bsl for OvenMediaEngine {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_stream_stats() {
+        let text = r#"
+            {
+                "message": "OK",
+                "response": {
+                    "avgThroughputIn": 3851276,
+                    "avgThroughputOut": 0,
+                    "connections": {
+                        "file": 0,
+                        "hlsv3": 0,
+                        "llhls": 2,
+                        "ovt": 0,
+                        "push": 0,
+                        "srt": 0,
+                        "thumbnail": 0,
+                        "webrtc": 5
+                    },
+                    "createdTime": "2023-05-12T20:13:21.341+09:00",
+                    "lastRecvTime": "2023-05-12T20:25:01.104+09:00",
+                    "lastSentTime": "2023-05-12T20:25:01.104+09:00",
+                    "lastThroughputIn": 4123904,
+                    "lastThroughputOut": 0,
+                    "lastUpdatedTime": "2023-05-12T20:25:01.104+09:00",
+                    "maxThroughputIn": 6918144,
+                    "maxThroughputOut": 0,
+                    "maxTotalConnectionTime": "2023-05-12T20:21:44.005+09:00",
+                    "maxTotalConnections": 9,
+                    "totalBytesIn": 336819200,
+                    "totalBytesOut": 0,
+                    "totalConnections": 7
+                },
+                "statusCode": 200
+            }
+        "#;
+
+        let parsed: OmeResponse = serde_json::from_str(text).unwrap();
+        println!("{:#?}", parsed);
+
+        let stats = parsed.response.unwrap();
+        assert_eq!(4027, stats.bitrate());
+        assert_eq!(7, stats.total_connections);
+    }
+
+    #[test]
+    fn stream_not_found() {
+        let text = r#"
+            {
+                "message": "Could not find the stream: [default/#default#app/live]",
+                "statusCode": 404
+            }
+        "#;
+
+        let parsed: OmeResponse = serde_json::from_str(text).unwrap();
+        assert!(parsed.response.is_none());
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+
+use super::{http::HttpClient, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
+
+/// A single sample from the text exposition format
+#[derive(Debug, PartialEq)]
+pub struct Sample {
+    pub name: String,
+    pub labels: HashMap<String, String>,
+    pub value: f64,
+}
+
+#[derive(Debug)]
+pub struct Stat {
+    /// Bitrate in Kbps
+    pub bitrate: u64,
+
+    /// RTT in ms
+    pub rtt: Option<f64>,
+}
+
+/// How the samples are combined when multiple series match a metric
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregation {
+    Sum,
+    Max,
+    Avg,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Metric {
+    /// Name of the metric
+    pub name: String,
+
+    /// Labels the metric needs to have
+    pub labels: Option<HashMap<String, String>>,
+
+    /// Multiply the value with this (ex; 1 / 1024 to convert bps to Kbps)
+    pub multiplier: Option<f64>,
+
+    /// How to combine the series that match, the bitrate gets summed and
+    /// the RTT uses the highest by default
+    pub aggregation: Option<Aggregation>,
+}
+
+impl Metric {
+    /// The samples matching the name and labels combined into one value
+    fn value(&self, samples: &[Sample], default: Aggregation) -> Option<f64> {
+        let matching = samples
+            .iter()
+            .filter(|s| s.name == self.name)
+            .filter(|s| match &self.labels {
+                Some(labels) => labels.iter().all(|(k, v)| s.labels.get(k) == Some(v)),
+                None => true,
+            })
+            .map(|s| s.value)
+            .collect::<Vec<f64>>();
+
+        if matching.is_empty() {
+            return None;
+        }
+
+        let sum: f64 = matching.iter().sum();
+
+        let value = match self.aggregation.unwrap_or(default) {
+            Aggregation::Sum => sum,
+            Aggregation::Max => matching.iter().copied().fold(f64::MIN, f64::max),
+            Aggregation::Avg => sum / matching.len() as f64,
+        };
+
+        Some(value * self.multiplier.unwrap_or(1.0))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Prometheus {
+    /// URL to the metrics endpoint (ex; http://127.0.0.1:9100/metrics )
+    pub stats_url: String,
+
+    /// Metric with the bitrate, the stream is offline when it's missing
+    pub bitrate: Metric,
+
+    /// Metric with the RTT
+    pub rtt: Option<Metric>,
+}
+
+impl Prometheus {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let res = client.send(client.get(&self.stats_url)).await?;
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing stats page ({})", self.stats_url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+        let samples = parse(&text);
+
+        // Bitrates of the tracks add up, one slow connection is enough for
+        // a high RTT
+        let bitrate = match self.bitrate.value(&samples, Aggregation::Sum) {
+            Some(bitrate) => bitrate,
+            None => return Ok(None),
+        };
+
+        let stream = Stat {
+            bitrate: bitrate.round() as u64,
+            rtt: self
+                .rtt
+                .as_ref()
+                .and_then(|m| m.value(&samples, Aggregation::Max)),
+        };
+
+        trace!("{:#?}", stream);
+        Ok(Some(stream))
+    }
+}
+
+/// Parses the Prometheus text exposition format, lines that can't be
+/// parsed are skipped
+pub fn parse(text: &str) -> Vec<Sample> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .filter_map(|l| {
+            let sample = parse_line(l);
+
+            if sample.is_none() {
+                trace!("Unable to parse metric: {}", l);
+            }
+
+            sample
+        })
+        .collect()
+}
+
+fn parse_line(line: &str) -> Option<Sample> {
+    let name_end = line.find(|c: char| c == '{' || c.is_whitespace())?;
+    let name = line[..name_end].to_string();
+    let mut rest = &line[name_end..];
+
+    let mut labels = HashMap::new();
+
+    if let Some(label_str) = rest.strip_prefix('{') {
+        let (parsed, remaining) = parse_labels(label_str)?;
+        labels = parsed;
+        rest = remaining;
+    }
+
+    // The timestamp after the value is ignored
+    let value = rest.split_whitespace().next()?.parse().ok()?;
+
+    Some(Sample {
+        name,
+        labels,
+        value,
+    })
+}
+
+/// Returns the labels and whatever is left after the closing brace
+fn parse_labels(text: &str) -> Option<(HashMap<String, String>, &str)> {
+    let mut labels = HashMap::new();
+    let mut chars = text.char_indices().peekable();
+
+    loop {
+        // Skip separators
+        while let Some((_, c)) = chars.peek() {
+            if *c == ',' || c.is_whitespace() {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+
+        let (start, c) = chars.next()?;
+
+        if c == '}' {
+            return Some((labels, &text[start + 1..]));
+        }
+
+        let mut key = c.to_string();
+        for (_, c) in chars.by_ref() {
+            if c == '=' {
+                break;
+            }
+
+            key.push(c);
+        }
+
+        if chars.next()?.1 != '"' {
+            return None;
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next()?.1 {
+                '"' => break,
+                '\\' => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+
+        labels.insert(key.trim().to_string(), value);
+    }
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for Prometheus {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        Ok(Some(StreamStats {
+            bitrate: stats.bitrate,
+            rtt: stats.rtt,
+            ..Default::default()
+        }))
+    }
+}
+
+#[typetag::serde]
+impl Bsl for Prometheus {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::switcher::{SwitchType, Triggers};
+    use warp::Filter;
+
+    const FIXTURE: &str = include_str!("testdata/prometheus.txt");
+
+    const BPS_TO_KBPS: f64 = 1.0 / 1024.0;
+
+    fn metric(name: &str, stream: &str, multiplier: f64) -> Metric {
+        let mut labels = HashMap::new();
+        labels.insert("app".to_string(), "publish".to_string());
+        labels.insert("stream".to_string(), stream.to_string());
+
+        Metric {
+            name: name.to_string(),
+            labels: Some(labels),
+            multiplier: Some(multiplier),
+            aggregation: None,
+        }
+    }
+
+    #[test]
+    fn parse_fixture() {
+        let samples = parse(FIXTURE);
+        println!("{:#?}", samples);
+
+        assert_eq!(7, samples.len());
+
+        let build_info = samples
+            .iter()
+            .find(|s| s.name == "relay_build_info")
+            .unwrap();
+        assert_eq!(
+            Some(&"relay \"edge\", eu-west {1}".to_string()),
+            build_info.labels.get("description")
+        );
+
+        let uptime = samples.last().unwrap();
+        assert_eq!("relay_uptime_seconds", uptime.name);
+        assert!(uptime.labels.is_empty());
+        assert_eq!(81234.0, uptime.value);
+    }
+
+    #[test]
+    fn sum_matching_labels() {
+        let samples = parse(FIXTURE);
+
+        let bitrate = metric("relay_ingest_bitrate_bps", "live", BPS_TO_KBPS);
+        let value = bitrate.value(&samples, Aggregation::Sum).unwrap();
+        assert!((value - 4225.0).abs() < 0.001);
+
+        let missing = metric("relay_ingest_bitrate_bps", "nothing", BPS_TO_KBPS);
+        assert_eq!(None, missing.value(&samples, Aggregation::Sum));
+    }
+
+    #[test]
+    fn aggregate_multiple_series() {
+        let samples = parse(FIXTURE);
+
+        let mut rtt = metric("relay_ingest_rtt_seconds", "live", 1000.0);
+        rtt.labels.as_mut().unwrap().remove("stream");
+
+        // Both streams match, the slowest one counts
+        let max = rtt.value(&samples, Aggregation::Max).unwrap();
+        assert!((max - 310.0).abs() < 0.001);
+
+        rtt.aggregation = Some(Aggregation::Avg);
+        let avg = rtt.value(&samples, Aggregation::Max).unwrap();
+        assert!((avg - 176.25).abs() < 0.001);
+
+        rtt.aggregation = Some(Aggregation::Sum);
+        let sum = rtt.value(&samples, Aggregation::Max).unwrap();
+        assert!((sum - 352.5).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn scrape_fixture() {
+        let route = warp::path("metrics").map(|| FIXTURE);
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let prometheus = Prometheus {
+            stats_url: format!("http://{}/metrics", addr),
+            bitrate: metric("relay_ingest_bitrate_bps", "live", BPS_TO_KBPS),
+            rtt: Some(metric("relay_ingest_rtt_seconds", "live", 1000.0)),
+        };
+
+        let client = HttpClient::default();
+        let stats = prometheus.stats(&client).await.unwrap().unwrap();
+        println!("{:#?}", stats);
+
+        assert_eq!(4225, stats.bitrate);
+        assert_eq!(Some(43.0), stats.rtt.map(f64::round));
+
+        let triggers = Triggers {
+            low: Some(5000),
+            ..Default::default()
+        };
+        assert_eq!(SwitchType::Low, triggers.switch_type(&stats, None));
+    }
+}
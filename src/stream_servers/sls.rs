@@ -3,8 +3,8 @@ use log::{error, trace};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use super::{Bsl, StreamServersCommands, SwitchLogic};
-use crate::switcher::{SwitchType, Triggers};
+use super::{http::HttpClient, srtla, stats::StreamStats, Bsl, SwitchLogic};
+use crate::error;
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
@@ -29,112 +29,74 @@ pub struct SrtLiveServer {
 
     /// StreamID of the where you are publishing the feed. (ex; publish/live/feed1 )
     pub publisher: String,
+
+    /// Optional SRTLA receiver when bonding multiple connections
+    pub srtla: Option<srtla::Srtla>,
 }
 
 impl SrtLiveServer {
-    pub async fn get_stats(&self) -> Option<Stat> {
-        let res = match reqwest::get(&self.stats_url).await {
-            Ok(res) => res,
-            Err(_) => {
-                error!("Stats page ({}) is unreachable", self.stats_url);
-                return None;
-            }
-        };
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let res = client.send(client.get(&self.stats_url)).await?;
 
         if res.status() != reqwest::StatusCode::OK {
             error!("Error accessing stats page ({})", self.stats_url);
-            return None;
+            return Ok(None);
         }
 
-        let text = res.text().await.ok()?;
-        let data: Value = serde_json::from_str(&text).ok()?;
-        let publisher = &data["publishers"][&self.publisher];
-
-        let stream: Stat = serde_json::from_value(publisher.to_owned()).ok()?;
-        // let stream: Stat = match serde_json::from_value(publisher.to_owned()) {
-        //     Ok(stats) => stats,
-        //     Err(error) => {
-        //         trace!("{}", &data);
-        //         error!("Error parsing stats ({}) {}", self.stats_url, error);
-        //         return None;
-        //     }
-        // };
-
-        trace!("{:#?}", stream);
-        Some(stream)
-    }
-}
-
-#[async_trait]
-#[typetag::serde]
-impl SwitchLogic for SrtLiveServer {
-    async fn switch(&self, triggers: &Triggers) -> SwitchType {
-        let stats = match self.get_stats().await {
-            Some(b) => b,
-            None => return SwitchType::Offline,
-        };
-
-        if let Some(offline) = triggers.offline {
-            if stats.bitrate > 0 && stats.bitrate <= offline.into() {
-                return SwitchType::Offline;
+        let text = res.text().await?;
+        let data: Value = match serde_json::from_str(&text) {
+            Ok(data) => data,
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", self.stats_url, error);
+                return Ok(None);
             }
-        }
-
-        if stats.bitrate == 0 {
-            return SwitchType::Previous;
-        }
+        };
 
-        if let Some(low) = triggers.low {
-            if stats.bitrate <= low.into() {
-                return SwitchType::Low;
-            }
-        }
+        // Not publishing
+        let publisher = match &data["publishers"][&self.publisher] {
+            Value::Null => return Ok(None),
+            publisher => publisher,
+        };
 
-        if let Some(rtt) = triggers.rtt {
-            if stats.rtt >= rtt.into() {
-                return SwitchType::Low;
+        let stream: Stat = match serde_json::from_value(publisher.to_owned()) {
+            Ok(stats) => stats,
+            Err(error) => {
+                trace!("{}", &data);
+                error!("Error parsing stats ({}) {}", self.stats_url, error);
+                return Ok(None);
             }
-        }
+        };
 
-        return SwitchType::Normal;
+        trace!("{:#?}", stream);
+        Ok(Some(stream))
     }
 }
 
 #[async_trait]
 #[typetag::serde]
-impl StreamServersCommands for SrtLiveServer {
-    async fn bitrate(&self) -> super::Bitrate {
-        let stats = match self.get_stats().await {
+impl SwitchLogic for SrtLiveServer {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
             Some(stats) => stats,
-            None => return super::Bitrate { message: None },
+            None => return Ok(None),
         };
 
-        let message = format!("{}, {} ms", stats.bitrate, stats.rtt.round());
-        super::Bitrate {
-            message: Some(message),
-        }
-    }
-
-    async fn source_info(&self) -> Option<String> {
-        let stats = self.get_stats().await?;
-
-        let bitrate = format!("{} Kbps, {} ms", stats.bitrate, stats.rtt.round());
-
-        let mbps = format!(
-            "Estimated bandwidth {} Mbps, Receiving rate {:.2} Mbps",
-            stats.mbps_bandwidth.round(),
-            stats.mbps_recv_rate
-        );
-
-        let pkt = format!(
-            "{} dropped, {} loss",
-            stats.pkt_rcv_drop, stats.pkt_rcv_loss
-        );
-
-        // The ms of acknowledged packets in the receiver's buffer
-        let ms_buf = format!("{} ms buffer", stats.ms_rcv_buf);
+        // The SRTLA receiver is optional, the stream works without it
+        let links = match &self.srtla {
+            Some(srtla) => srtla.get_stats(client).await.ok().flatten(),
+            None => None,
+        };
 
-        Some(format!("{} | {} | {} |  {}", bitrate, mbps, pkt, ms_buf))
+        Ok(Some(StreamStats {
+            bitrate: stats.bitrate.max(0) as u64,
+            rtt: Some(stats.rtt),
+            packets_lost: Some(stats.pkt_rcv_loss.max(0) as u64),
+            packets_dropped: Some(stats.pkt_rcv_drop.max(0) as u64),
+            uptime: Some(stats.uptime.max(0) as u64),
+            links: links.map(|l| l.links()),
+            ..Default::default()
+        }))
     }
 }
 
@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+/// How the bitrate samples in the window get combined
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Aggregation {
+    Mean,
+    Median,
+
+    /// Exponential moving average, recent samples weigh more
+    Ema,
+
+    /// 10th percentile, only stays high when most samples are high
+    P10,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Smoothing {
+    /// Amount of polls to use, 1 uses the bitrate as is
+    pub window: usize,
+
+    pub aggregation: Aggregation,
+}
+
+impl Default for Smoothing {
+    fn default() -> Self {
+        Self {
+            window: 1,
+            aggregation: Aggregation::Mean,
+        }
+    }
+}
+
+/// Bitrate samples of the last polls of a stream server
+#[derive(Debug, Default)]
+pub struct History {
+    samples: VecDeque<u64>,
+}
+
+impl History {
+    /// Adds the bitrate and returns the smoothed bitrate over the window
+    pub fn push(&mut self, bitrate: u64, smoothing: &Smoothing) -> u64 {
+        let window = smoothing.window.max(1);
+
+        self.samples.push_back(bitrate);
+
+        while self.samples.len() > window {
+            self.samples.pop_front();
+        }
+
+        aggregate(&self.samples, smoothing.aggregation)
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+fn aggregate(samples: &VecDeque<u64>, aggregation: Aggregation) -> u64 {
+    if samples.is_empty() {
+        return 0;
+    }
+
+    let len = samples.len();
+
+    match aggregation {
+        Aggregation::Mean => samples.iter().sum::<u64>() / len as u64,
+        Aggregation::Median => {
+            let sorted = sorted(samples);
+
+            if len % 2 == 0 {
+                (sorted[len / 2 - 1] + sorted[len / 2]) / 2
+            } else {
+                sorted[len / 2]
+            }
+        }
+        Aggregation::Ema => {
+            let alpha = 2.0 / (len as f64 + 1.0);
+
+            let ema = samples
+                .iter()
+                .skip(1)
+                .fold(samples[0] as f64, |ema, &bitrate| {
+                    alpha * bitrate as f64 + (1.0 - alpha) * ema
+                });
+
+            ema.round() as u64
+        }
+        Aggregation::P10 => {
+            // Nearest rank
+            let rank = (len as f64 * 0.1).ceil() as usize;
+
+            sorted(samples)[rank.max(1) - 1]
+        }
+    }
+}
+
+fn sorted(samples: &VecDeque<u64>) -> Vec<u64> {
+    let mut sorted: Vec<u64> = samples.iter().copied().collect();
+    sorted.sort_unstable();
+
+    sorted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn smoothed(samples: &[u64], aggregation: Aggregation) -> u64 {
+        let smoothing = Smoothing {
+            window: 5,
+            aggregation,
+        };
+
+        let mut history = History::default();
+
+        samples
+            .iter()
+            .map(|&bitrate| history.push(bitrate, &smoothing))
+            .last()
+            .unwrap()
+    }
+
+    #[test]
+    fn single_dip() {
+        // The first sample falls outside of the window
+        let samples = [0, 6000, 6000, 200, 6000, 6000];
+
+        assert_eq!(4840, smoothed(&samples, Aggregation::Mean));
+        assert_eq!(6000, smoothed(&samples, Aggregation::Median));
+        assert_eq!(5141, smoothed(&samples, Aggregation::Ema));
+        assert_eq!(200, smoothed(&samples, Aggregation::P10));
+    }
+
+    #[test]
+    fn no_smoothing() {
+        let smoothing = Smoothing::default();
+        let mut history = History::default();
+
+        assert_eq!(6000, history.push(6000, &smoothing));
+        assert_eq!(200, history.push(200, &smoothing));
+    }
+}
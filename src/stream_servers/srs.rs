@@ -0,0 +1,407 @@
+use async_trait::async_trait;
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    http::HttpClient,
+    stats::{Resolution, StreamStats},
+    Bsl, SwitchLogic,
+};
+use crate::error;
+
+/// Streams and clients are paginated, SRS only returns 10 when no count is
+/// requested
+const PAGE_SIZE: usize = 100;
+
+/// Stops paging when a server keeps returning full pages, like one that
+/// ignores the start parameter would
+const MAX_PAGES: usize = 100;
+
+#[derive(Deserialize, Debug)]
+struct SrsStreams {
+    streams: Vec<SrsStream>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SrsStream {
+    pub id: String,
+    pub name: String,
+    pub vhost: String,
+    pub app: String,
+    pub kbps: Kbps,
+    pub publish: Publish,
+    pub video: Option<Video>,
+    pub audio: Option<Audio>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Kbps {
+    pub recv_30s: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Publish {
+    pub active: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+pub struct Video {
+    codec: String,
+    profile: Option<String>,
+    level: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Audio {
+    codec: String,
+    sample_rate: Option<u32>,
+    channel: Option<u32>,
+    profile: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SrsClients {
+    clients: Vec<SrsClient>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SrsClient {
+    pub stream: String,
+    pub publish: bool,
+    pub kbps: Kbps,
+}
+
+#[derive(Deserialize, Debug)]
+struct SrsVhosts {
+    vhosts: Vec<SrsVhost>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SrsVhost {
+    id: String,
+    name: String,
+}
+
+pub struct Stat {
+    pub stream: SrsStream,
+    pub publisher: Option<SrsClient>,
+}
+
+impl Stat {
+    /// Bitrate of the publisher, falls back to the stream bitrate
+    pub fn bitrate(&self) -> u32 {
+        match &self.publisher {
+            Some(publisher) => publisher.kbps.recv_30s,
+            None => self.stream.kbps.recv_30s,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Srs {
+    /// URL to the SRS HTTP API (ex; http://127.0.0.1:1985 )
+    pub stats_url: String,
+
+    /// Vhost name, the default vhost will be used when not set
+    pub vhost: Option<String>,
+
+    /// Stream application
+    pub application: String,
+
+    /// Stream key
+    pub key: String,
+}
+
+impl Srs {
+    async fn get_json<T>(&self, client: &HttpClient, path: &str) -> Result<Option<T>, error::Error>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let url = format!("{}/api/v1/{}", &self.stats_url, path);
+
+        let res = client.send(client.get(&url)).await?;
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing stats page ({})", url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+
+        match serde_json::from_str(&text) {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing stats ({}) {}", url, error);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Requests every page of a paginated list
+    async fn get_all<P, T>(
+        &self,
+        client: &HttpClient,
+        path: &str,
+        items: fn(P) -> Vec<T>,
+    ) -> Result<Option<Vec<T>>, error::Error>
+    where
+        P: serde::de::DeserializeOwned,
+    {
+        let mut all = Vec::new();
+
+        for _ in 0..MAX_PAGES {
+            let page_path = format!("{}?start={}&count={}", path, all.len(), PAGE_SIZE);
+
+            let page = match self.get_json(client, &page_path).await? {
+                Some(page) => items(page),
+                None => return Ok(None),
+            };
+
+            let last = page.len() < PAGE_SIZE;
+            all.extend(page);
+
+            if last {
+                break;
+            }
+        }
+
+        Ok(Some(all))
+    }
+
+    /// SRS identifies vhosts by id in the streams list
+    async fn get_vhost_id(&self, client: &HttpClient) -> Result<Option<String>, error::Error> {
+        let name = match &self.vhost {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+
+        let vhosts: SrsVhosts = match self.get_json(client, "vhosts/").await? {
+            Some(vhosts) => vhosts,
+            None => return Ok(None),
+        };
+
+        Ok(vhosts
+            .vhosts
+            .into_iter()
+            .find(|v| &v.name == name)
+            .map(|v| v.id))
+    }
+
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<Stat>, error::Error> {
+        let streams = match self
+            .get_all(client, "streams/", |s: SrsStreams| s.streams)
+            .await?
+        {
+            Some(streams) => streams,
+            None => return Ok(None),
+        };
+
+        let vhost_id = self.get_vhost_id(client).await?;
+
+        if self.vhost.is_some() && vhost_id.is_none() {
+            error!("Vhost {:?} not found", self.vhost);
+            return Ok(None);
+        }
+
+        let stream = match find_stream(streams, vhost_id.as_deref(), &self.application, &self.key) {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+
+        let publisher = self
+            .get_all(client, "clients/", |c: SrsClients| c.clients)
+            .await?
+            .and_then(|clients| {
+                clients
+                    .into_iter()
+                    .find(|c| c.publish && c.stream == stream.id)
+            });
+
+        let stat = Stat { stream, publisher };
+
+        trace!("{:#?}", stat.stream);
+        Ok(Some(stat))
+    }
+}
+
+fn find_stream(
+    streams: Vec<SrsStream>,
+    vhost_id: Option<&str>,
+    application: &str,
+    key: &str,
+) -> Option<SrsStream> {
+    streams.into_iter().find(|s| {
+        s.app == application && s.name == key && vhost_id.map_or(true, |id| s.vhost == id)
+    })
+}
+
+#[async_trait]
+#[typetag::serde]
+impl SwitchLogic for Srs {
+    async fn stats(&self, client: &HttpClient) -> Result<Option<StreamStats>, error::Error> {
+        let stats = match self.get_stats(client).await? {
+            Some(stats) => stats,
+            None => return Ok(None),
+        };
+
+        if !stats.stream.publish.active {
+            return Ok(None);
+        }
+
+        let mut stream_stats = StreamStats {
+            bitrate: stats.bitrate().into(),
+            ..Default::default()
+        };
+
+        if let Some(video) = &stats.stream.video {
+            if let (Some(width), Some(height)) = (video.width, video.height) {
+                stream_stats.resolution = Some(Resolution { width, height });
+            }
+            stream_stats.video_codec = Some(video.codec.to_owned());
+        }
+
+        if let Some(audio) = &stats.stream.audio {
+            stream_stats.audio_codec = Some(audio.codec.to_owned());
+            stream_stats.audio_profile = audio.profile.to_owned();
+            stream_stats.audio_sample_rate = audio.sample_rate;
+            stream_stats.audio_channels = audio.channel;
+        }
+
+        Ok(Some(stream_stats))
+    }
+}
+
+#[typetag::serde]
+impl Bsl for Srs {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+    use warp::Filter;
+
+    use super::*;
+
+    const STREAMS: &str = r#"
+        {
+            "code": 0,
+            "server": "vid-0xk989d",
+            "service": "4k1y8u2b",
+            "pid": "1",
+            "streams": [
+                {
+                    "id": "vid-82u9341",
+                    "name": "live",
+                    "vhost": "vid-1o3m9d3",
+                    "app": "publish",
+                    "tcUrl": "rtmp://127.0.0.1/publish",
+                    "url": "/publish/live",
+                    "live_ms": 1665661262410,
+                    "clients": 1,
+                    "frames": 0,
+                    "send_bytes": 0,
+                    "recv_bytes": 12493057,
+                    "kbps": { "recv_30s": 2484, "send_30s": 0 },
+                    "publish": { "active": true, "cid": "ak7t4m51" },
+                    "video": {
+                        "codec": "H264",
+                        "profile": "High",
+                        "level": "3.1",
+                        "width": 1280,
+                        "height": 720
+                    },
+                    "audio": {
+                        "codec": "AAC",
+                        "sample_rate": 44100,
+                        "channel": 2,
+                        "profile": "LC"
+                    }
+                },
+                {
+                    "id": "vid-7k4d913",
+                    "name": "live",
+                    "vhost": "vid-1o3m9d3",
+                    "app": "other",
+                    "tcUrl": "rtmp://127.0.0.1/other",
+                    "url": "/other/live",
+                    "live_ms": 1665661262410,
+                    "clients": 0,
+                    "frames": 0,
+                    "send_bytes": 0,
+                    "recv_bytes": 0,
+                    "kbps": { "recv_30s": 0, "send_30s": 0 },
+                    "publish": { "active": false },
+                    "video": null,
+                    "audio": null
+                }
+            ]
+        }
+    "#;
+
+    #[test]
+    fn find_stream_by_app_and_key() {
+        let parsed: SrsStreams = serde_json::from_str(STREAMS).unwrap();
+        println!("{:#?}", parsed);
+
+        let stream = find_stream(parsed.streams, None, "publish", "live").unwrap();
+        assert_eq!("vid-82u9341", stream.id);
+        assert_eq!(2484, stream.kbps.recv_30s);
+    }
+
+    #[test]
+    fn find_stream_wrong_vhost() {
+        let parsed: SrsStreams = serde_json::from_str(STREAMS).unwrap();
+
+        let stream = find_stream(parsed.streams, Some("vid-doesnotexist"), "publish", "live");
+        assert!(stream.is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_on_a_later_page() {
+        let streams: Vec<serde_json::Value> = (0..250)
+            .map(|i| {
+                json!({
+                    "id": format!("vid-{}", i),
+                    "name": format!("key{}", i),
+                    "vhost": "vid-1o3m9d3",
+                    "app": "publish",
+                    "kbps": { "recv_30s": i, "send_30s": 0 },
+                    "publish": { "active": true },
+                    "video": null,
+                    "audio": null
+                })
+            })
+            .collect();
+
+        let route = warp::path!("api" / "v1" / "streams" / ..)
+            .and(warp::query::<HashMap<String, usize>>())
+            .map(move |query: HashMap<String, usize>| {
+                let start = query.get("start").copied().unwrap_or(0);
+                let count = query.get("count").copied().unwrap_or(10);
+                let page: Vec<_> = streams.iter().skip(start).take(count).collect();
+
+                warp::reply::json(&json!({ "code": 0, "streams": page }))
+            })
+            .or(warp::path!("api" / "v1" / "clients" / ..)
+                .map(|| warp::reply::json(&json!({ "code": 0, "clients": [] }))));
+
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let srs = Srs {
+            stats_url: format!("http://{}", addr),
+            vhost: None,
+            application: "publish".to_string(),
+            key: "key234".to_string(),
+        };
+
+        let stats = srs.stats(&HttpClient::default()).await.unwrap().unwrap();
+        assert_eq!(234, stats.bitrate);
+    }
+}
@@ -0,0 +1,102 @@
+use log::{error, trace};
+use serde::{Deserialize, Serialize};
+
+use super::{http::HttpClient, stats::LinkStats};
+use crate::error;
+
+/// Stats of a SRTLA receiver, used next to a SRT server when bonding
+/// multiple connections
+#[derive(Deserialize, Debug)]
+pub struct SrtlaStats {
+    pub links: Vec<Link>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Link {
+    /// Address the link is connected from
+    pub address: String,
+
+    /// Kbps received on this link
+    pub bitrate: u64,
+}
+
+impl SrtlaStats {
+    pub fn total_bitrate(&self) -> u64 {
+        self.links.iter().map(|l| l.bitrate).sum()
+    }
+
+    /// Every link with its share of the total bitrate
+    pub fn links(&self) -> Vec<LinkStats> {
+        let total = self.total_bitrate();
+
+        self.links
+            .iter()
+            .map(|l| LinkStats {
+                addr: l.address.to_owned(),
+                bitrate: l.bitrate,
+                share: if total > 0 {
+                    l.bitrate * 100 / total
+                } else {
+                    0
+                },
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Srtla {
+    /// URL to the SRTLA receiver stats page (ex; http://127.0.0.1:8282/stats )
+    pub stats_url: String,
+}
+
+impl Srtla {
+    pub async fn get_stats(&self, client: &HttpClient) -> Result<Option<SrtlaStats>, error::Error> {
+        let res = client.send(client.get(&self.stats_url)).await?;
+
+        if res.status() != reqwest::StatusCode::OK {
+            error!("Error accessing SRTLA stats page ({})", self.stats_url);
+            return Ok(None);
+        }
+
+        let text = res.text().await?;
+        let stats: SrtlaStats = match serde_json::from_str(&text) {
+            Ok(stats) => stats,
+            Err(error) => {
+                trace!("{}", &text);
+                error!("Error parsing SRTLA stats ({}) {}", self.stats_url, error);
+                return Ok(None);
+            }
+        };
+
+        trace!("{:#?}", stats);
+        Ok(Some(stats))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_with_share() {
+        let text = r#"
+            {
+                "links": [
+                    { "address": "10.64.12.7:41234", "bitrate": 3000 },
+                    { "address": "100.88.1.21:50311", "bitrate": 1000 },
+                    { "address": "172.20.10.2:60002", "bitrate": 0 }
+                ]
+            }
+        "#;
+
+        let parsed: SrtlaStats = serde_json::from_str(text).unwrap();
+        println!("{:#?}", parsed);
+
+        assert_eq!(
+            "2/3 links: 10.64.12.7:41234 3000 Kbps (75%), 100.88.1.21:50311 1000 Kbps (25%), 172.20.10.2:60002 0 Kbps (0%)",
+            crate::stream_servers::stats::links_info(&parsed.links(), "en")
+        );
+    }
+}
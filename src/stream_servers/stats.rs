@@ -0,0 +1,387 @@
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+/// Packets are assumed to be this big when a stream server doesn't report
+/// the amount of received packets, the usual SRT payload size
+const SRT_PACKET_BYTES: f64 = 1316.0;
+
+/// Stats of a stream that every stream server reports in the same units
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamStats {
+    /// Bitrate in Kbps
+    pub bitrate: u64,
+
+    /// Round trip time in ms
+    pub rtt: Option<f64>,
+
+    /// Total amount of received packets
+    pub packets_received: Option<u64>,
+
+    /// Total amount of lost packets
+    pub packets_lost: Option<u64>,
+
+    /// Total amount of dropped packets
+    pub packets_dropped: Option<u64>,
+
+    /// Percentage of packets lost since the last poll
+    pub loss_percent: Option<f64>,
+
+    /// Packets dropped per second since the last poll
+    pub dropped_per_second: Option<f64>,
+
+    /// Seconds since the stream started
+    pub uptime: Option<u64>,
+
+    pub resolution: Option<Resolution>,
+    pub fps: Option<f64>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub audio_profile: Option<String>,
+
+    /// Audio sample rate in Hz
+    pub audio_sample_rate: Option<u32>,
+    pub audio_channels: Option<u32>,
+
+    /// Links of a SRTLA receiver when bonding multiple connections
+    pub links: Option<Vec<LinkStats>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Resolution {
+    /// Parses resolutions like 1920x1080
+    pub fn parse(text: &str) -> Option<Self> {
+        let (width, height) = text.split_once('x')?;
+
+        Some(Self {
+            width: width.trim().parse().ok()?,
+            height: height.trim().parse().ok()?,
+        })
+    }
+}
+
+/// A single connection of a SRTLA receiver
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinkStats {
+    /// Address the link is connected from
+    pub addr: String,
+
+    /// Bitrate in Kbps
+    pub bitrate: u64,
+
+    /// Percentage of the total bitrate of all links
+    pub share: u64,
+}
+
+/// Every link with its bitrate and share, so a weak link stands out
+pub fn links_info(links: &[LinkStats], lang: &str) -> String {
+    let alive = links.iter().filter(|l| l.bitrate > 0).count();
+    let total = links.len();
+
+    let links = links
+        .iter()
+        .map(|l| {
+            t!(
+                "sourceinfo.link",
+                locale = lang,
+                addr = &l.addr,
+                bitrate = &l.bitrate.to_string(),
+                share = &l.share.to_string()
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    t!(
+        "sourceinfo.links",
+        locale = lang,
+        alive = &alive.to_string(),
+        total = &total.to_string(),
+        links = &links
+    )
+}
+
+impl StreamStats {
+    /// SRTLA links that are still carrying traffic
+    pub fn links_alive(&self) -> Option<u32> {
+        let links = self.links.as_ref()?;
+
+        Some(links.iter().filter(|l| l.bitrate > 0).count() as u32)
+    }
+
+    /// Message used by the bitrate chat command
+    pub fn bitrate_message(&self, lang: &str) -> String {
+        let bitrate = self.bitrate.to_string();
+
+        match self.rtt {
+            Some(rtt) => t!(
+                "bitrate.messageRtt",
+                locale = lang,
+                bitrate = &bitrate,
+                rtt = &rtt.round().to_string()
+            ),
+            None => t!("bitrate.message", locale = lang, bitrate = &bitrate),
+        }
+    }
+
+    /// Message used by the sourceinfo chat command
+    pub fn source_info(&self, lang: &str) -> String {
+        let mut info = vec![t!(
+            "sourceinfo.bitrate",
+            locale = lang,
+            bitrate = &self.bitrate.to_string()
+        )];
+
+        if let Some(rtt) = self.rtt {
+            info.push(t!(
+                "sourceinfo.rtt",
+                locale = lang,
+                rtt = &rtt.round().to_string()
+            ));
+        }
+
+        let mut video = Vec::new();
+
+        if let Some(resolution) = self.resolution {
+            video.push(format!("{}x{}", resolution.width, resolution.height));
+        }
+
+        if let Some(fps) = self.fps {
+            video.push(t!(
+                "sourceinfo.fps",
+                locale = lang,
+                fps = &fps.round().to_string()
+            ));
+        }
+
+        video.extend(self.video_codec.to_owned());
+
+        if !video.is_empty() {
+            info.push(video.join(" "));
+        }
+
+        let mut audio: Vec<String> = self
+            .audio_codec
+            .iter()
+            .chain(&self.audio_profile)
+            .cloned()
+            .collect();
+
+        if let Some(rate) = self.audio_sample_rate {
+            audio.push(t!(
+                "sourceinfo.sampleRate",
+                locale = lang,
+                rate = &rate.to_string()
+            ));
+        }
+
+        if let Some(channels) = self.audio_channels {
+            audio.push(t!(
+                "sourceinfo.channels",
+                locale = lang,
+                count = &channels.to_string()
+            ));
+        }
+
+        if !audio.is_empty() {
+            info.push(audio.join(" "));
+        }
+
+        if let Some(lost) = self.packets_lost {
+            info.push(t!(
+                "sourceinfo.lost",
+                locale = lang,
+                count = &lost.to_string()
+            ));
+        }
+
+        if let Some(dropped) = self.packets_dropped {
+            info.push(t!(
+                "sourceinfo.dropped",
+                locale = lang,
+                count = &dropped.to_string()
+            ));
+        }
+
+        if let Some(links) = &self.links {
+            info.push(links_info(links, lang));
+        }
+
+        if let Some(uptime) = self.uptime {
+            info.push(t!(
+                "sourceinfo.uptime",
+                locale = lang,
+                uptime = &format_duration(uptime)
+            ));
+        }
+
+        info.join(" | ")
+    }
+}
+
+/// Packet counters of the last poll, used to calculate the loss and drop
+/// rates
+#[derive(Debug, Default)]
+pub struct PacketCounters {
+    last: Option<CounterSample>,
+}
+
+#[derive(Debug)]
+struct CounterSample {
+    time: Instant,
+    received: Option<u64>,
+    lost: Option<u64>,
+    dropped: Option<u64>,
+}
+
+impl PacketCounters {
+    /// Fills in the loss and drop rates since the last poll
+    pub fn update(&mut self, stats: &mut StreamStats, now: Instant) {
+        let sample = CounterSample {
+            time: now,
+            received: stats.packets_received,
+            lost: stats.packets_lost,
+            dropped: stats.packets_dropped,
+        };
+
+        let last = match self.last.replace(sample) {
+            Some(last) => last,
+            None => return,
+        };
+
+        let elapsed = now.duration_since(last.time).as_secs_f64();
+
+        if elapsed <= 0.0 {
+            return;
+        }
+
+        if let Some(lost) = delta(last.lost, stats.packets_lost) {
+            // Estimate the received packets from the bitrate when the
+            // stream server doesn't report them
+            let received = match delta(last.received, stats.packets_received) {
+                Some(received) => received as f64,
+                None => stats.bitrate as f64 * 1024.0 / 8.0 / SRT_PACKET_BYTES * elapsed,
+            };
+
+            let total = received + lost as f64;
+
+            stats.loss_percent = Some(if total > 0.0 {
+                lost as f64 * 100.0 / total
+            } else {
+                0.0
+            });
+        }
+
+        stats.dropped_per_second =
+            delta(last.dropped, stats.packets_dropped).map(|dropped| dropped as f64 / elapsed);
+    }
+
+    pub fn clear(&mut self) {
+        self.last = None;
+    }
+}
+
+/// None when the counter isn't available or got reset
+fn delta(last: Option<u64>, current: Option<u64>) -> Option<u64> {
+    current?.checked_sub(last?)
+}
+
+/// Formats seconds as h:mm:ss
+pub(crate) fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_info_message() {
+        let stats = StreamStats {
+            bitrate: 4520,
+            rtt: Some(38.4),
+            packets_dropped: Some(12),
+            uptime: Some(3725),
+            resolution: Resolution::parse("1280x720"),
+            fps: Some(29.97),
+            video_codec: Some("H264".to_string()),
+            audio_codec: Some("AAC".to_string()),
+            audio_profile: Some("LC".to_string()),
+            audio_sample_rate: Some(48000),
+            audio_channels: Some(2),
+            links: Some(vec![
+                LinkStats {
+                    addr: "10.64.12.7:41234".to_string(),
+                    bitrate: 4520,
+                    share: 100,
+                },
+                LinkStats {
+                    addr: "172.20.10.2:60002".to_string(),
+                    bitrate: 0,
+                    share: 0,
+                },
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!("4520, 38 ms", stats.bitrate_message("en"));
+        assert_eq!(
+            "4520 Kbps | 38 ms | 1280x720 30 fps H264 | AAC LC 48000 Hz 2 channels | 12 dropped packets | 1/2 links: 10.64.12.7:41234 4520 Kbps (100%), 172.20.10.2:60002 0 Kbps (0%) | live for 1:02:05",
+            stats.source_info("en")
+        );
+    }
+
+    #[test]
+    fn packet_rates() {
+        let mut counters = PacketCounters::default();
+        let now = Instant::now();
+
+        let mut stats = StreamStats {
+            bitrate: 5000,
+            packets_received: Some(1000),
+            packets_lost: Some(10),
+            packets_dropped: Some(4),
+            ..Default::default()
+        };
+
+        counters.update(&mut stats, now);
+        assert_eq!(None, stats.loss_percent);
+        assert_eq!(None, stats.dropped_per_second);
+
+        let mut stats = StreamStats {
+            bitrate: 5000,
+            packets_received: Some(1950),
+            packets_lost: Some(60),
+            packets_dropped: Some(24),
+            ..Default::default()
+        };
+
+        counters.update(&mut stats, now + std::time::Duration::from_secs(2));
+        assert_eq!(Some(5.0), stats.loss_percent);
+        assert_eq!(Some(10.0), stats.dropped_per_second);
+
+        // Counters got reset by the stream server
+        let mut stats = StreamStats {
+            bitrate: 5000,
+            packets_received: Some(20),
+            packets_lost: Some(0),
+            packets_dropped: Some(0),
+            ..Default::default()
+        };
+
+        counters.update(&mut stats, now + std::time::Duration::from_secs(3));
+        assert_eq!(None, stats.loss_percent);
+        assert_eq!(None, stats.dropped_per_second);
+    }
+}
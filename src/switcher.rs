@@ -1,13 +1,18 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use futures_util::future;
 use serde::{Deserialize, Serialize};
-use tokio::sync::Notify;
-use tracing::{debug, error, info, Instrument};
+use tokio::{
+    sync::Notify,
+    time::{Instant, MissedTickBehavior},
+};
+use tracing::{debug, error, info, warn, Instrument};
 
 use crate::{
-    chat, error,
+    chat, error, events,
     noalbs::{self, ChatSender},
-    state::ClientStatus,
+    recording,
+    state::{ClientStatus, State},
     stream_servers,
 };
 
@@ -21,28 +26,190 @@ impl Switcher {
         tracing::info!("Running switcher");
 
         let f = async move {
-            let mut prev_switch_type: SwitchType = SwitchType::Offline;
-            let mut same_type: u8 = 0;
+            let poll_loop = async {
+                let mut interval = tokio::time::interval(Duration::from_secs(1));
+                interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
 
-            loop {
-                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                tracing::debug!("Switcher loop");
+                loop {
+                    interval.tick().await;
+                    tracing::debug!("Poll loop");
 
-                if let Some(notifier) = switcher.get_sleep_notifier_if_necessary().await {
-                    notifier.notified().await;
-                    continue;
+                    switcher.poll_stream_servers().await;
                 }
+            };
+
+            let switch_loop = async {
+                let mut prev_switch_type: SwitchType = SwitchType::Offline;
+                let mut same_type: u8 = 0;
+                let mut last_switch: Option<(SwitchType, Instant)> = None;
+
+                let snapshot_notifier = switcher
+                    .state
+                    .read()
+                    .await
+                    .switcher_state
+                    .snapshot_notifier();
+
+                loop {
+                    snapshot_notifier.notified().await;
+                    tracing::debug!("Switcher loop");
+
+                    if let Some(notifier) = switcher.get_sleep_notifier_if_necessary().await {
+                        notifier.notified().await;
+                        continue;
+                    }
 
-                if let Err(e) = switcher.switch(&mut prev_switch_type, &mut same_type).await {
-                    error!("Error when trying to switch: {}", e);
+                    if let Err(e) = switcher
+                        .switch(
+                            &mut prev_switch_type,
+                            &mut same_type,
+                            &mut last_switch,
+                            Instant::now(),
+                        )
+                        .await
+                    {
+                        error!("Error when trying to switch: {}", e);
+                    }
                 }
-            }
+            };
+
+            let schedule_loop = async {
+                let mut interval = tokio::time::interval(Duration::from_secs(30));
+                let mut last_scheduled: Option<String> = None;
+
+                loop {
+                    interval.tick().await;
+
+                    switcher.apply_scheduled_profile(&mut last_scheduled).await;
+                }
+            };
+
+            tokio::join!(poll_loop, switch_loop, schedule_loop);
         }
         .instrument(tracing::info_span!("Switcher"));
 
         tokio::spawn(f)
     }
 
+    /// Polls all stream servers at the same time and stores the snapshot
+    pub(crate) async fn poll_stream_servers(&self) {
+        let (polls, record_stats) = {
+            let state = self.state.read().await;
+            let switcher_config = &state.config.switcher;
+            let deadline = Duration::from_millis(switcher_config.poll_deadline_ms);
+
+            let record_stats = match &switcher_config.record_stats {
+                Some(path) if state.broadcasting_software.is_streaming => Some(path.to_owned()),
+                _ => None,
+            };
+
+            let polls: Vec<_> = switcher_config
+                .stream_servers
+                .iter()
+                .map(|server| {
+                    let name = server.name.to_owned();
+                    let poll = server.poll(deadline);
+
+                    async move { (name, poll.await) }
+                })
+                .collect();
+
+            (polls, record_stats)
+        };
+
+        // Not holding the lock so a slow stream server doesn't block the writers
+        let polled = future::join_all(polls).await;
+        let polled_at = Instant::now();
+
+        let polled = polled.into_iter().map(|(name, stats)| {
+            let stats = match stats {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    debug!("Stream server {} unreachable: {}", name, e);
+                    None
+                }
+            };
+
+            (name, stats)
+        });
+
+        let snapshot = Self::evaluate_polls(&*self.state.read().await, polled, polled_at);
+
+        if let Some(path) = record_stats {
+            if let Err(e) = recording::append(&path, &recording::Tick::new(&snapshot)).await {
+                error!("Unable to record the stats to {:?}: {}", path, e);
+            }
+        }
+
+        let mut state = self.state.write().await;
+        state.switcher_state.snapshot = snapshot;
+        state.switcher_state.snapshot_notifier().notify_waiters();
+    }
+
+    /// Evaluates the stats of the polled stream servers into a snapshot, the
+    /// stats are None when the stats page was unreachable. Stream servers
+    /// that got removed while polling are left out
+    pub(crate) fn evaluate_polls<I>(
+        state: &State,
+        polled: I,
+        now: Instant,
+    ) -> stream_servers::Snapshot
+    where
+        I: IntoIterator<Item = (String, Option<Option<stream_servers::stats::StreamStats>>)>,
+    {
+        let switcher_config = &state.config.switcher;
+        let triggers = switcher_config.triggers();
+        let last_snapshot = &state.switcher_state.snapshot;
+
+        let servers = polled
+            .into_iter()
+            .filter_map(|(name, stats)| {
+                let server = switcher_config
+                    .stream_servers
+                    .iter()
+                    .find(|s| s.name == name)?;
+
+                let status = stats.map(|stats| {
+                    let prev = last_snapshot.status(&name).map(|s| s.switch_type);
+                    server.evaluate(stats, &triggers, prev, now)
+                });
+
+                Some((name, status))
+            })
+            .collect();
+
+        stream_servers::Snapshot { servers }
+    }
+
+    /// Activates the scheduled profile when the schedule moves on to another
+    /// profile, so a profile set from chat stays till then
+    async fn apply_scheduled_profile(&self, last_scheduled: &mut Option<String>) {
+        let state = self.state.read().await;
+
+        let scheduled = match &state.config.switcher.schedule {
+            Some(schedule) => schedule.profile_at(&chrono::Utc::now()).map(str::to_owned),
+            None => return,
+        };
+
+        if &scheduled == last_scheduled {
+            return;
+        }
+
+        drop(state);
+        *last_scheduled = scheduled.to_owned();
+
+        let name = match scheduled {
+            Some(name) => name,
+            None => return,
+        };
+
+        if self.state.write().await.apply_profile(&name) {
+            info!("Activated scheduled profile {}", name);
+        } else {
+            warn!("Scheduled profile {} doesn't exist", name);
+        }
+    }
+
     pub async fn get_sleep_notifier_if_necessary(&self) -> Option<Arc<Notify>> {
         let state = self.state.read().await;
 
@@ -77,21 +244,31 @@ impl Switcher {
         None
     }
 
-    async fn switch(
+    pub(crate) async fn switch(
         &self,
         prev_switch_type: &mut SwitchType,
         same_type: &mut u8,
+        last_switch: &mut Option<(SwitchType, Instant)>,
+        now: Instant,
     ) -> Result<(), error::Error> {
         let state = self.state.read().await;
 
         let switcher_config = &state.config.switcher;
-        let triggers = &switcher_config.triggers;
         let stream_servers = &switcher_config.stream_servers;
         let retry_attempts = &switcher_config.retry_attempts;
         let instant_recover = &switcher_config.instantly_switch_on_recover;
 
+        let snapshot = &state.switcher_state.snapshot;
+
         let (mut server, mut current_switch_type) =
-            Self::get_online_stream_server(stream_servers, triggers).await;
+            match Self::get_online_stream_server(stream_servers, snapshot) {
+                Ok(online) => online,
+                Err(e) if switcher_config.hold_scene_when_unreachable => {
+                    warn!("Holding the current scene: {}", e);
+                    return Ok(());
+                }
+                Err(_) => (None, SwitchType::Offline),
+            };
 
         // When stream comes back from offline, instantly switch.
         let mut force_switch = *instant_recover
@@ -99,7 +276,7 @@ impl Switcher {
             && current_switch_type != SwitchType::Offline;
 
         if prev_switch_type == &current_switch_type {
-            *same_type += 1;
+            *same_type = same_type.saturating_add(1);
         } else {
             debug!("Got different type, switching to that");
 
@@ -120,10 +297,28 @@ impl Switcher {
             }
         }
 
-        if !(same_type == retry_attempts || force_switch) {
+        if !(*same_type >= *retry_attempts || force_switch) {
             return Ok(());
         }
 
+        // Stay in the current scene for at least the dwell time
+        if let Some((last_type, since)) = last_switch {
+            let leaving =
+                *last_type != current_switch_type && current_switch_type != SwitchType::Previous;
+
+            let triggers = &switcher_config.triggers;
+
+            if let Some(dwell) = triggers.dwell.get(last_type, &triggers.tiers) {
+                if leaving && now.saturating_duration_since(*since) < dwell {
+                    debug!(
+                        "Staying in the {:?} scene for at least {:?}",
+                        last_type, dwell
+                    );
+                    return Ok(());
+                }
+            }
+        }
+
         *same_type = 0;
 
         if current_switch_type == SwitchType::Offline {
@@ -132,17 +327,25 @@ impl Switcher {
             }
         }
 
-        let scenes = if let Some(scenes) = self.get_optional_scenes(server).await {
-            scenes
-        } else {
-            &switcher_config.switching_scenes
-        };
+        let optional_scenes = Self::get_optional_scenes(server, snapshot);
+        let scenes = optional_scenes.unwrap_or_else(|| switcher_config.switching_scenes());
 
-        let scene = if let SwitchType::Previous = &current_switch_type {
-            &state.broadcasting_software.prev_scene
-        } else {
-            // Should be safe since previous is handled
-            scenes.type_to_scene(&current_switch_type).unwrap()
+        let scene = match &current_switch_type {
+            SwitchType::Previous => &state.broadcasting_software.prev_scene,
+            SwitchType::Tier(index) => {
+                let tier = switcher_config
+                    .triggers
+                    .tiers
+                    .get(*index)
+                    .ok_or(error::Error::TierNotFound(*index))?;
+
+                match optional_scenes {
+                    Some(scenes) => scenes.tier_to_scene(tier),
+                    None => &tier.scene,
+                }
+            }
+            // Should be safe since previous and tiers are handled
+            _ => scenes.type_to_scene(&current_switch_type).unwrap(),
         }
         .to_owned();
 
@@ -153,9 +356,13 @@ impl Switcher {
         {
             let mut state = self.state.write().await;
 
-            // Set the previous scene when switch_type is normal or low
-            if let SwitchType::Normal | SwitchType::Low = current_switch_type {
-                state.broadcasting_software.prev_scene = scene.to_owned();
+            // Set the previous scene when switch_type is normal, low or a tier.
+            // A dry run doesn't switch so the previous scene stays the same
+            if let SwitchType::Normal | SwitchType::Low | SwitchType::Tier(_) = current_switch_type
+            {
+                if !state.config.switcher.dry_run {
+                    state.broadcasting_software.prev_scene = scene.to_owned();
+                }
             };
 
             if current_switch_type != SwitchType::Offline {
@@ -164,36 +371,63 @@ impl Switcher {
             }
         }
 
-        self.switch_if_necessary(&scene, current_switch_type)
+        let switched = self
+            .switch_if_necessary(&scene, current_switch_type)
             .await?;
 
+        // The dwell time only starts when the scene actually changed, a dry
+        // run never switches so it leaves the dwell time alone
+        let new_type = current_switch_type != SwitchType::Previous
+            && last_switch.map_or(true, |(last_type, _)| last_type != current_switch_type);
+
+        if switched && new_type {
+            *last_switch = Some((current_switch_type, now));
+        }
+
         Ok(())
     }
 
-    /// Gets the first online stream server with current status
-    async fn get_online_stream_server<'a>(
+    /// Gets the first online stream server from the last poll.
+    ///
+    /// Errors when no stream server is online and at least one of the
+    /// stats pages was unreachable.
+    fn get_online_stream_server<'a>(
         stream_servers: &'a [stream_servers::StreamServer],
-        triggers: &'a Triggers,
-    ) -> (Option<&'a stream_servers::StreamServer>, SwitchType) {
+        snapshot: &stream_servers::Snapshot,
+    ) -> Result<(Option<&'a stream_servers::StreamServer>, SwitchType), error::Error> {
+        let mut unreachable = false;
+
         for server in stream_servers {
-            let switch_type = server.stream_server.switch(triggers).await;
+            if snapshot.is_unreachable(&server.name) {
+                unreachable = true;
+                continue;
+            }
+
+            let switch_type = match snapshot.status(&server.name) {
+                Some(status) => status.switch_type,
+                None => continue,
+            };
 
             if switch_type == SwitchType::Offline {
                 continue;
             }
 
-            return (Some(server), switch_type);
+            return Ok((Some(server), switch_type));
         }
 
-        (None, SwitchType::Offline)
+        if unreachable {
+            return Err(error::Error::StatsPageNotAvailable);
+        }
+
+        Ok((None, SwitchType::Offline))
     }
 
-    async fn get_optional_scenes<'a>(
-        &'a self,
+    fn get_optional_scenes<'a>(
         server: Option<&'a stream_servers::StreamServer>,
+        snapshot: &stream_servers::Snapshot,
     ) -> Option<&'a SwitchingScenes> {
         if let Some(depends) = &server?.depends_on {
-            if !self.is_stream_server_online(&depends.name).await {
+            if !Self::is_stream_server_online(&depends.name, snapshot) {
                 debug!("The depended stream server is offline. Going to use the backup scenes.");
                 return Some(&depends.backup_scenes);
             }
@@ -202,27 +436,18 @@ impl Switcher {
         server?.override_scenes.as_ref()
     }
 
-    async fn is_stream_server_online(&self, server_name: &str) -> bool {
-        match self
-            .state
-            .read()
-            .await
-            .config
-            .switcher
-            .stream_servers
-            .iter()
-            .find(|&x| x.name == server_name)
-        {
-            Some(server) => server.stream_server.bitrate().await.message.is_some(),
-            None => false,
-        }
+    fn is_stream_server_online(server_name: &str, snapshot: &stream_servers::Snapshot) -> bool {
+        snapshot
+            .status(server_name)
+            .map_or(false, |status| status.stats.is_some())
     }
 
+    /// Switches to the scene when that's possible, returns if it switched
     pub async fn switch_if_necessary(
         &self,
         switch_scene: &str,
         switch_type: SwitchType,
-    ) -> Result<(), error::Error> {
+    ) -> Result<bool, error::Error> {
         debug!(
             "Switch scene: {} Switch type: {:?}",
             switch_scene, switch_type
@@ -231,7 +456,7 @@ impl Switcher {
         let state = &self.state.read().await;
 
         if state.broadcasting_software.current_scene == switch_scene {
-            return Ok(());
+            return Ok(false);
         }
 
         if !state
@@ -239,7 +464,21 @@ impl Switcher {
             .switchable_scenes
             .contains(&state.broadcasting_software.current_scene)
         {
-            return Ok(());
+            return Ok(false);
+        }
+
+        if state.config.switcher.dry_run {
+            info!(
+                "Dry run, would switch to [{:?}] {}",
+                switch_type, switch_scene
+            );
+
+            state.send_event(&events::Event::DryRunSwitch {
+                scene: switch_scene,
+                switch_type,
+            });
+
+            return Ok(false);
         }
 
         // Ignore the error.. it should work at some point
@@ -252,7 +491,7 @@ impl Switcher {
             .await
         {
             error!("Switch scene error {:?}", error);
-            return Ok(());
+            return Ok(false);
         }
 
         info!("Scene switched to [{:?}] {}", switch_type, switch_scene);
@@ -273,7 +512,7 @@ impl Switcher {
             }
         }
 
-        Ok(())
+        Ok(true)
     }
 }
 
@@ -283,6 +522,10 @@ pub struct SwitchingScenes {
     pub normal: String,
     pub low: String,
     pub offline: String,
+
+    /// Scenes that replace the scene of a tier, keyed by tier name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tiers: HashMap<String, String>,
 }
 
 impl SwitchingScenes {
@@ -296,9 +539,16 @@ impl SwitchingScenes {
             normal: normal.into(),
             low: low.into(),
             offline: offline.into(),
+            tiers: HashMap::new(),
         }
     }
 
+    /// Scene of the tier when these scenes replace the switching scenes,
+    /// the normal scene when they don't have a scene for the tier
+    pub fn tier_to_scene(&self, tier: &Tier) -> &str {
+        self.tiers.get(&tier.name).unwrap_or(&self.normal)
+    }
+
     pub fn type_to_scene(&self, s_type: &SwitchType) -> Result<&str, error::Error> {
         Ok(match s_type {
             SwitchType::Normal => &self.normal,
@@ -309,46 +559,1009 @@ impl SwitchingScenes {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TriggerType {
     Low,
     Rtt,
     Offline,
+    Loss,
+    Dropped,
+    Links,
+    LowRecover,
+    RttRecover,
+    OfflineRecover,
+    LossRecover,
+    DroppedRecover,
+    LinksRecover,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl TriggerType {
+    /// The trigger used to leave the state again
+    pub fn recover(self) -> Self {
+        match self {
+            TriggerType::Low => TriggerType::LowRecover,
+            TriggerType::Rtt => TriggerType::RttRecover,
+            TriggerType::Offline => TriggerType::OfflineRecover,
+            TriggerType::Loss => TriggerType::LossRecover,
+            TriggerType::Dropped => TriggerType::DroppedRecover,
+            TriggerType::Links => TriggerType::LinksRecover,
+            recover => recover,
+        }
+    }
+
+    /// Trigger by the name used in chat (ex; loss)
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_lowercase().as_ref() {
+            "low" => TriggerType::Low,
+            "rtt" => TriggerType::Rtt,
+            "offline" => TriggerType::Offline,
+            "loss" => TriggerType::Loss,
+            "dropped" => TriggerType::Dropped,
+            "links" => TriggerType::Links,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Triggers {
     /// Trigger to switch to the low scene
     pub low: Option<u32>,
 
+    /// Bitrate needed to leave the low scene again, uses low when not set
+    pub low_recover: Option<u32>,
+
     /// Trigger to switch to the low scene when RTT is high
     pub rtt: Option<u32>,
 
+    /// RTT needed to leave the low scene again, uses rtt when not set
+    pub rtt_recover: Option<u32>,
+
     /// Trigger to switch to the offline scene
     pub offline: Option<u32>,
+
+    /// Bitrate needed to leave the offline scene again, uses offline when
+    /// not set
+    pub offline_recover: Option<u32>,
+
+    /// Trigger to switch to the low scene when the percentage of lost
+    /// packets since the last poll is high
+    pub loss: Option<u32>,
+
+    /// Loss needed to leave the low scene again, uses loss when not set
+    pub loss_recover: Option<u32>,
+
+    /// Trigger to switch to the low scene when too many packets per second
+    /// get dropped
+    pub dropped: Option<u32>,
+
+    /// Dropped packets per second needed to leave the low scene again, uses
+    /// dropped when not set
+    pub dropped_recover: Option<u32>,
+
+    /// Trigger to switch to the low scene when fewer SRTLA links are alive
+    pub links: Option<u32>,
+
+    /// Links needed to leave the low scene again, uses links when not set
+    pub links_recover: Option<u32>,
+
+    /// Minimum time to stay in a scene before switching away from it
+    #[serde(default)]
+    pub dwell: Dwell,
+
+    /// Smooths the bitrate over the last polls before checking the triggers
+    #[serde(default)]
+    pub smoothing: stream_servers::smoothing::Smoothing,
+
+    /// Tiers ordered from best to worst, tiers with a bitrate below the low
+    /// trigger are used instead of the low scene
+    #[serde(default)]
+    pub tiers: Vec<Tier>,
+}
+
+/// Quality tier with its own scene
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tier {
+    pub name: String,
+
+    /// Scene to switch to when in this tier
+    pub scene: String,
+
+    /// Switch to this tier when the bitrate is at or below this value
+    pub bitrate: Option<u32>,
+
+    /// Bitrate needed to leave this tier again, uses bitrate when not set
+    pub recover: Option<u32>,
+
+    /// Switch to this tier when the RTT is at or above this value
+    pub rtt: Option<u32>,
+}
+
+impl Tier {
+    /// Whether the stats are bad enough for this tier, the recover bitrate
+    /// is used when the stream is already in this tier
+    pub fn matches(&self, stats: &stream_servers::stats::StreamStats, current: bool) -> bool {
+        if let Some(bitrate) = self.bitrate {
+            let bitrate = match (current, self.recover) {
+                (true, Some(recover)) => recover.max(bitrate),
+                _ => bitrate,
+            };
+
+            if stats.bitrate <= bitrate.into() {
+                return true;
+            }
+        }
+
+        if let (Some(trigger), Some(rtt)) = (self.rtt, stats.rtt) {
+            if rtt >= trigger.into() {
+                return true;
+            }
+        }
+
+        false
+    }
+}
+
+/// Triggers of a stream server that replace the global ones field by field,
+/// 0 disables the trigger for that stream server
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerOverrides {
+    pub low: Option<u32>,
+    pub low_recover: Option<u32>,
+    pub rtt: Option<u32>,
+    pub rtt_recover: Option<u32>,
+    pub offline: Option<u32>,
+    pub offline_recover: Option<u32>,
+    pub loss: Option<u32>,
+    pub loss_recover: Option<u32>,
+    pub dropped: Option<u32>,
+    pub dropped_recover: Option<u32>,
+    pub links: Option<u32>,
+    pub links_recover: Option<u32>,
+    pub smoothing: Option<stream_servers::smoothing::Smoothing>,
+}
+
+impl TriggerOverrides {
+    pub fn set(&mut self, kind: &TriggerType, value: u32) {
+        *self.trigger_mut(kind) = Some(value);
+    }
+
+    /// Goes back to the global trigger
+    pub fn clear(&mut self, kind: &TriggerType) {
+        *self.trigger_mut(kind) = None;
+    }
+
+    fn trigger_mut(&mut self, kind: &TriggerType) -> &mut Option<u32> {
+        match kind {
+            TriggerType::Low => &mut self.low,
+            TriggerType::Rtt => &mut self.rtt,
+            TriggerType::Offline => &mut self.offline,
+            TriggerType::Loss => &mut self.loss,
+            TriggerType::Dropped => &mut self.dropped,
+            TriggerType::Links => &mut self.links,
+            TriggerType::LowRecover => &mut self.low_recover,
+            TriggerType::RttRecover => &mut self.rtt_recover,
+            TriggerType::OfflineRecover => &mut self.offline_recover,
+            TriggerType::LossRecover => &mut self.loss_recover,
+            TriggerType::DroppedRecover => &mut self.dropped_recover,
+            TriggerType::LinksRecover => &mut self.links_recover,
+        }
+    }
+}
+
+/// Triggers that replace the current ones field by field, fields that are
+/// left out keep their value and 0 disables the trigger
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggersUpdate {
+    pub low: Option<u32>,
+    pub low_recover: Option<u32>,
+    pub rtt: Option<u32>,
+    pub rtt_recover: Option<u32>,
+    pub offline: Option<u32>,
+    pub offline_recover: Option<u32>,
+    pub loss: Option<u32>,
+    pub loss_recover: Option<u32>,
+    pub dropped: Option<u32>,
+    pub dropped_recover: Option<u32>,
+    pub links: Option<u32>,
+    pub links_recover: Option<u32>,
+    pub dwell: Option<Dwell>,
+    pub smoothing: Option<stream_servers::smoothing::Smoothing>,
+    pub tiers: Option<Vec<Tier>>,
+}
+
+/// Minimum time in seconds to stay in each scene
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dwell {
+    pub normal: Option<u32>,
+    pub low: Option<u32>,
+    pub offline: Option<u32>,
+
+    /// Used for every tier that isn't in tiers
+    pub tier: Option<u32>,
+
+    /// Dwell time of a single tier, keyed by tier name
+    #[serde(default)]
+    pub tiers: HashMap<String, u32>,
+}
+
+impl Dwell {
+    pub fn get(&self, switch_type: &SwitchType, tiers: &[Tier]) -> Option<Duration> {
+        let seconds = match switch_type {
+            SwitchType::Normal => self.normal,
+            SwitchType::Low => self.low,
+            SwitchType::Offline => self.offline,
+            SwitchType::Tier(index) => self.by_name(&tiers.get(*index)?.name),
+            SwitchType::Previous => None,
+        }?;
+
+        Some(Duration::from_secs(seconds.into()))
+    }
+
+    /// Seconds by the scene name used in chat (normal, low, offline, tier or
+    /// the name of a tier)
+    pub fn by_name(&self, scene: &str) -> Option<u32> {
+        match scene {
+            "normal" => self.normal,
+            "low" => self.low,
+            "offline" => self.offline,
+            "tier" => self.tier,
+            name => self.tiers.get(name).copied().or(self.tier),
+        }
+    }
+
+    pub fn set_by_name(&mut self, scene: &str, seconds: Option<u32>) {
+        match (scene, seconds) {
+            ("normal", _) => self.normal = seconds,
+            ("low", _) => self.low = seconds,
+            ("offline", _) => self.offline = seconds,
+            ("tier", _) => self.tier = seconds,
+            (name, Some(seconds)) => {
+                self.tiers.insert(name.to_owned(), seconds);
+            }
+            (name, None) => {
+                self.tiers.remove(name);
+            }
+        }
+    }
 }
 
 impl Triggers {
     pub fn set_low(&mut self, value: Option<u32>) {
         self.low = value;
     }
+
+    pub fn get(&self, kind: &TriggerType) -> Option<u32> {
+        match kind {
+            TriggerType::Low => self.low,
+            TriggerType::Rtt => self.rtt,
+            TriggerType::Offline => self.offline,
+            TriggerType::Loss => self.loss,
+            TriggerType::Dropped => self.dropped,
+            TriggerType::Links => self.links,
+            TriggerType::LowRecover => self.low_recover,
+            TriggerType::RttRecover => self.rtt_recover,
+            TriggerType::OfflineRecover => self.offline_recover,
+            TriggerType::LossRecover => self.loss_recover,
+            TriggerType::DroppedRecover => self.dropped_recover,
+            TriggerType::LinksRecover => self.links_recover,
+        }
+    }
+
+    pub fn set(&mut self, kind: &TriggerType, value: Option<u32>) {
+        match kind {
+            TriggerType::Low => self.low = value,
+            TriggerType::Rtt => self.rtt = value,
+            TriggerType::Offline => self.offline = value,
+            TriggerType::Loss => self.loss = value,
+            TriggerType::Dropped => self.dropped = value,
+            TriggerType::Links => self.links = value,
+            TriggerType::LowRecover => self.low_recover = value,
+            TriggerType::RttRecover => self.rtt_recover = value,
+            TriggerType::OfflineRecover => self.offline_recover = value,
+            TriggerType::LossRecover => self.loss_recover = value,
+            TriggerType::DroppedRecover => self.dropped_recover = value,
+            TriggerType::LinksRecover => self.links_recover = value,
+        }
+    }
+
+    /// Merges the fields that are set in the update
+    pub fn update(&mut self, update: TriggersUpdate) {
+        let merge = |current: &mut Option<u32>, value: Option<u32>| match value {
+            Some(0) => *current = None,
+            Some(value) => *current = Some(value),
+            None => {}
+        };
+
+        merge(&mut self.low, update.low);
+        merge(&mut self.low_recover, update.low_recover);
+        merge(&mut self.rtt, update.rtt);
+        merge(&mut self.rtt_recover, update.rtt_recover);
+        merge(&mut self.offline, update.offline);
+        merge(&mut self.offline_recover, update.offline_recover);
+        merge(&mut self.loss, update.loss);
+        merge(&mut self.loss_recover, update.loss_recover);
+        merge(&mut self.dropped, update.dropped);
+        merge(&mut self.dropped_recover, update.dropped_recover);
+        merge(&mut self.links, update.links);
+        merge(&mut self.links_recover, update.links_recover);
+
+        if let Some(dwell) = update.dwell {
+            self.dwell = dwell;
+        }
+
+        if let Some(smoothing) = update.smoothing {
+            self.smoothing = smoothing;
+        }
+
+        if let Some(tiers) = update.tiers {
+            self.tiers = tiers;
+        }
+    }
+
+    /// The triggers with the overrides of a stream server applied
+    pub fn with_overrides(&self, overrides: &TriggerOverrides) -> Self {
+        let merge = |global: Option<u32>, value: Option<u32>| match value {
+            Some(0) => None,
+            Some(value) => Some(value),
+            None => global,
+        };
+
+        Self {
+            low: merge(self.low, overrides.low),
+            low_recover: merge(self.low_recover, overrides.low_recover),
+            rtt: merge(self.rtt, overrides.rtt),
+            rtt_recover: merge(self.rtt_recover, overrides.rtt_recover),
+            offline: merge(self.offline, overrides.offline),
+            offline_recover: merge(self.offline_recover, overrides.offline_recover),
+            loss: merge(self.loss, overrides.loss),
+            loss_recover: merge(self.loss_recover, overrides.loss_recover),
+            dropped: merge(self.dropped, overrides.dropped),
+            dropped_recover: merge(self.dropped_recover, overrides.dropped_recover),
+            links: merge(self.links, overrides.links),
+            links_recover: merge(self.links_recover, overrides.links_recover),
+            dwell: self.dwell.to_owned(),
+            smoothing: overrides
+                .smoothing
+                .to_owned()
+                .unwrap_or_else(|| self.smoothing.to_owned()),
+            tiers: self.tiers.to_owned(),
+        }
+    }
+
+    /// Which scene to switch to based on the stats and the previous switch
+    /// type of the stream server. The recover triggers are used to leave the
+    /// previous state so a stream hovering around a trigger doesn't flap.
+    ///
+    /// Stream server specific states, like a zero bitrate when the stream
+    /// just started, should be handled before calling this.
+    pub fn switch_type(
+        &self,
+        stats: &stream_servers::stats::StreamStats,
+        prev: Option<SwitchType>,
+    ) -> SwitchType {
+        let bitrate = stats.bitrate;
+
+        if let Some(offline) = self.offline {
+            let offline = match (prev, self.offline_recover) {
+                (Some(SwitchType::Offline), Some(recover)) => recover.max(offline),
+                _ => offline,
+            };
+
+            if bitrate > 0 && bitrate <= offline.into() {
+                return SwitchType::Offline;
+            }
+        }
+
+        let low = self.is_low(stats, prev);
+
+        // The worst tier that matches, only tiers below the low trigger are
+        // worse than the low scene
+        let tier = self
+            .tiers
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(index, tier)| tier.matches(stats, prev == Some(SwitchType::Tier(*index))));
+
+        match (tier, low) {
+            (Some((index, tier)), true) if self.is_below_low(tier) => SwitchType::Tier(index),
+            (_, true) => SwitchType::Low,
+            (Some((index, _)), false) => SwitchType::Tier(index),
+            (None, false) => SwitchType::Normal,
+        }
+    }
+
+    /// Whether the low trigger or any of the triggers that switch to the
+    /// low scene match
+    fn is_low(&self, stats: &stream_servers::stats::StreamStats, prev: Option<SwitchType>) -> bool {
+        let bitrate = stats.bitrate;
+
+        if let Some(low) = self.low {
+            let low = match (prev, self.low_recover) {
+                (Some(SwitchType::Low), Some(recover)) => recover.max(low),
+                _ => low,
+            };
+
+            if bitrate <= low.into() {
+                return true;
+            }
+        }
+
+        if let (Some(trigger), Some(rtt)) = (self.rtt, stats.rtt) {
+            let trigger = match (prev, self.rtt_recover) {
+                (Some(SwitchType::Low), Some(recover)) => recover.min(trigger),
+                _ => trigger,
+            };
+
+            if rtt >= trigger.into() {
+                return true;
+            }
+        }
+
+        if let (Some(trigger), Some(loss)) = (self.loss, stats.loss_percent) {
+            let trigger = match (prev, self.loss_recover) {
+                (Some(SwitchType::Low), Some(recover)) => recover.min(trigger),
+                _ => trigger,
+            };
+
+            if loss >= trigger.into() {
+                return true;
+            }
+        }
+
+        if let (Some(trigger), Some(dropped)) = (self.dropped, stats.dropped_per_second) {
+            let trigger = match (prev, self.dropped_recover) {
+                (Some(SwitchType::Low), Some(recover)) => recover.min(trigger),
+                _ => trigger,
+            };
+
+            if dropped >= trigger.into() {
+                return true;
+            }
+        }
+
+        if let (Some(trigger), Some(alive)) = (self.links, stats.links_alive()) {
+            let trigger = match (prev, self.links_recover) {
+                (Some(SwitchType::Low), Some(recover)) => recover.max(trigger),
+                _ => trigger,
+            };
+
+            if alive < trigger {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn is_below_low(&self, tier: &Tier) -> bool {
+        matches!((tier.bitrate, self.low), (Some(bitrate), Some(low)) if bitrate < low)
+    }
 }
 
 impl Default for Triggers {
     fn default() -> Self {
         Self {
             low: Some(800),
+            low_recover: None,
             rtt: Some(2500),
+            rtt_recover: None,
             offline: None,
+            offline_recover: None,
+            loss: None,
+            loss_recover: None,
+            dropped: None,
+            dropped_recover: None,
+            links: None,
+            links_recover: None,
+            dwell: Dwell::default(),
+            smoothing: Default::default(),
+            tiers: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub enum SwitchType {
     Normal,
     Low,
     Previous,
     Offline,
+
+    /// Index of the tier in the triggers
+    Tier(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config, recording,
+        stream_servers::{
+            mock::{MockServer, MockStep},
+            stats::StreamStats,
+        },
+    };
+    use serde_json::json;
+
+    fn config(switcher: serde_json::Value) -> config::Config {
+        serde_json::from_value(json!({
+            "user": { "name": "test" },
+            "switcher": switcher,
+            "software": { "type": "Obs", "host": "localhost", "port": 4444 },
+            "optionalScenes": {},
+            "optionalOptions": {}
+        }))
+        .unwrap()
+    }
+
+    fn mock_server(name: &str, script: Vec<MockStep>) -> serde_json::Value {
+        let server: Box<dyn stream_servers::Bsl> = Box::new(MockServer::scripted(script));
+
+        json!({ "streamServer": server, "name": name })
+    }
+
+    /// Polls and switches every second, returns the second and scene of
+    /// every switch
+    async fn timeline(config: config::Config, seconds: u64) -> Vec<(u64, String)> {
+        run(&mut recording::Simulation::new(config), seconds).await
+    }
+
+    async fn run(simulation: &mut recording::Simulation, seconds: u64) -> Vec<(u64, String)> {
+        let mut timeline = Vec::new();
+
+        for second in 0..seconds {
+            simulation.switcher.poll_stream_servers().await;
+
+            if let Some(scene) = simulation.switch(Instant::now()).await.unwrap() {
+                timeline.push((second, scene));
+            }
+
+            tokio::time::advance(Duration::from_secs(1)).await;
+        }
+
+        timeline
+    }
+
+    async fn set_dry_run(simulation: &recording::Simulation, enabled: bool) {
+        let mut state = simulation.switcher.state.write().await;
+        state.config.switcher.dry_run = enabled;
+    }
+
+    fn scenes(timeline: &[(u64, &str)]) -> Vec<(u64, String)> {
+        timeline
+            .iter()
+            .map(|(second, scene)| (*second, scene.to_string()))
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn low_offline_recover() {
+        let config = config(json!({
+            "retryAttempts": 2,
+            "triggers": { "low": 800 },
+            "streamServers": [mock_server("srt", vec![
+                MockStep::online(3, 5000),
+                MockStep::online(4, 300),
+                MockStep::offline(4),
+                MockStep::online(1, 5000),
+            ])]
+        }));
+
+        assert_eq!(
+            scenes(&[(0, "live"), (5, "low"), (9, "offline"), (11, "live")]),
+            timeline(config, 13).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dwell_time() {
+        let config = config(json!({
+            "retryAttempts": 2,
+            "triggers": { "low": 800, "dwell": { "low": 10 } },
+            "streamServers": [mock_server("srt", vec![
+                MockStep::online(2, 5000),
+                MockStep::online(3, 300),
+                MockStep::online(1, 5000),
+            ])]
+        }));
+
+        assert_eq!(
+            scenes(&[(0, "live"), (4, "low"), (14, "live")]),
+            timeline(config, 16).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn tier_override_scenes() {
+        let mut srt = mock_server(
+            "srt",
+            vec![
+                MockStep::online(1, 5000),
+                MockStep::online(3, 2000),
+                MockStep::online(6, 5000),
+            ],
+        );
+        srt["overrideScenes"] = json!({
+            "normal": "srt",
+            "low": "srt low",
+            "offline": "offline",
+            "tiers": { "degraded": "srt degraded" }
+        });
+
+        let config = config(json!({
+            "retryAttempts": 2,
+            "triggers": {
+                "low": 800,
+                "dwell": { "tier": 5 },
+                "tiers": [{ "name": "degraded", "scene": "live degraded", "bitrate": 3000 }]
+            },
+            "streamServers": [srt]
+        }));
+
+        // Stays in the tier for the dwell time before going back to live
+        assert_eq!(
+            scenes(&[(0, "srt"), (3, "srt degraded"), (8, "srt")]),
+            timeline(config, 10).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_switch_has_no_dwell() {
+        let config = config(json!({
+            "retryAttempts": 2,
+            "triggers": { "low": 800, "dwell": { "low": 10 } },
+            "streamServers": [mock_server("srt", vec![
+                MockStep::online(1, 300),
+                MockStep::online(5, 5000),
+            ])]
+        }));
+
+        let mut simulation = recording::Simulation::new(config);
+        simulation.software.fail_next_switch();
+
+        // The switch to low failed, so the low dwell doesn't hold back live
+        assert_eq!(scenes(&[(3, "live")]), run(&mut simulation, 6).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dry_run_has_no_dwell() {
+        let config = config(json!({
+            "retryAttempts": 2,
+            "triggers": { "low": 800, "dwell": { "low": 10 } },
+            "streamServers": [mock_server("srt", vec![
+                MockStep::online(1, 300),
+                MockStep::online(5, 5000),
+            ])]
+        }));
+
+        let mut simulation = recording::Simulation::new(config);
+        set_dry_run(&simulation, true).await;
+
+        assert_eq!(scenes(&[]), run(&mut simulation, 2).await);
+
+        set_dry_run(&simulation, false).await;
+
+        // Only pretended to switch to low, so the low dwell doesn't hold back live
+        assert_eq!(scenes(&[(1, "live")]), run(&mut simulation, 4).await);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unreachable_stats_page() {
+        let switcher = |hold: bool| {
+            json!({
+                "retryAttempts": 2,
+                "holdSceneWhenUnreachable": hold,
+                "streamServers": [mock_server("srt", vec![
+                    MockStep::online(2, 5000),
+                    MockStep::unreachable(1),
+                ])]
+            })
+        };
+
+        assert_eq!(
+            scenes(&[(0, "live"), (4, "offline")]),
+            timeline(config(switcher(false)), 7).await
+        );
+        assert_eq!(
+            scenes(&[(0, "live")]),
+            timeline(config(switcher(true)), 7).await
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn depends_on_backup_scenes() {
+        let mut srt = mock_server("srt", vec![MockStep::online(1, 5000)]);
+        srt["dependsOn"] = json!({
+            "name": "rtmp",
+            "backupScenes": { "normal": "backup", "low": "backup low", "offline": "offline" }
+        });
+
+        let config = config(json!({
+            "retryAttempts": 2,
+            "streamServers": [
+                srt,
+                mock_server("rtmp", vec![MockStep::offline(2), MockStep::online(1, 5000)]),
+            ]
+        }));
+
+        assert_eq!(
+            scenes(&[(0, "backup"), (2, "live")]),
+            timeline(config, 4).await
+        );
+    }
+
+    #[test]
+    fn optional_scenes() {
+        let mut srt = mock_server("srt", Vec::new());
+        srt["overrideScenes"] = json!({ "normal": "srt", "low": "srt low", "offline": "offline" });
+        srt["dependsOn"] = json!({
+            "name": "rtmp",
+            "backupScenes": { "normal": "backup", "low": "backup low", "offline": "offline" }
+        });
+
+        let config = config(json!({ "streamServers": [srt] }));
+        let server = config.switcher.stream_servers.first();
+
+        let online = stream_servers::Status {
+            switch_type: SwitchType::Normal,
+            stats: Some(StreamStats::default()),
+        };
+
+        let mut snapshot = stream_servers::Snapshot::default();
+        snapshot.servers.insert("rtmp".to_string(), Some(online));
+
+        let scenes = Switcher::get_optional_scenes(server, &snapshot).unwrap();
+        assert_eq!("srt", scenes.normal);
+
+        snapshot
+            .servers
+            .insert("rtmp".to_string(), Some(stream_servers::Status::offline()));
+
+        let scenes = Switcher::get_optional_scenes(server, &snapshot).unwrap();
+        assert_eq!("backup", scenes.normal);
+
+        assert!(Switcher::get_optional_scenes(None, &snapshot).is_none());
+    }
+
+    #[test]
+    fn stream_server_overrides() {
+        let triggers = Triggers::default();
+
+        let overrides = TriggerOverrides {
+            low: Some(2000),
+            rtt: Some(0),
+            ..Default::default()
+        };
+
+        let merged = triggers.with_overrides(&overrides);
+
+        assert_eq!(Some(2000), merged.low);
+        assert_eq!(None, merged.rtt);
+        assert_eq!(triggers.offline, merged.offline);
+
+        let mut overrides = overrides;
+        overrides.clear(&TriggerType::Low);
+
+        assert_eq!(triggers.low, triggers.with_overrides(&overrides).low);
+    }
+
+    #[test]
+    fn partial_triggers_update() {
+        let mut triggers = Triggers {
+            offline: Some(200),
+            low_recover: Some(1200),
+            dwell: Dwell {
+                low: Some(10),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let update: TriggersUpdate = serde_json::from_str(r#"{ "low": 1000, "rtt": 0 }"#).unwrap();
+        triggers.update(update);
+
+        assert_eq!(Some(1000), triggers.low);
+        assert_eq!(None, triggers.rtt);
+
+        // Left out of the update
+        assert_eq!(Some(200), triggers.offline);
+        assert_eq!(Some(1200), triggers.low_recover);
+        assert_eq!(Some(10), triggers.dwell.low);
+    }
+
+    #[test]
+    fn tiers() {
+        let tier = |name: &str, bitrate| Tier {
+            name: name.to_string(),
+            scene: name.to_string(),
+            bitrate: Some(bitrate),
+            recover: None,
+            rtt: None,
+        };
+
+        let triggers = Triggers {
+            tiers: vec![
+                tier("degraded", 3000),
+                tier("poor", 1500),
+                tier("critical", 500),
+            ],
+            ..Default::default()
+        };
+
+        let switch_type = |bitrate| {
+            let stats = StreamStats {
+                bitrate,
+                ..Default::default()
+            };
+
+            triggers.switch_type(&stats, None)
+        };
+
+        assert_eq!(SwitchType::Normal, switch_type(5000));
+        assert_eq!(SwitchType::Tier(0), switch_type(2500));
+        assert_eq!(SwitchType::Tier(1), switch_type(1200));
+        assert_eq!(SwitchType::Low, switch_type(700));
+
+        // Below the low trigger
+        assert_eq!(SwitchType::Tier(2), switch_type(400));
+    }
+
+    #[test]
+    fn tier_boundaries() {
+        let triggers: Triggers = serde_json::from_value(json!({
+            "low": 800,
+            "lowRecover": 1000,
+            "tiers": [
+                { "name": "degraded", "scene": "degraded", "bitrate": 3000, "recover": 3500 },
+                { "name": "critical", "scene": "critical", "bitrate": 500, "recover": 600 }
+            ]
+        }))
+        .unwrap();
+
+        let switch_type = |bitrate, prev| {
+            let stats = StreamStats {
+                bitrate,
+                ..Default::default()
+            };
+
+            triggers.switch_type(&stats, Some(prev))
+        };
+
+        // Going down into the tier and back up past its recover bitrate
+        assert_eq!(SwitchType::Tier(0), switch_type(2900, SwitchType::Normal));
+        assert_eq!(SwitchType::Tier(0), switch_type(3200, SwitchType::Tier(0)));
+        assert_eq!(SwitchType::Normal, switch_type(3200, SwitchType::Normal));
+        assert_eq!(SwitchType::Normal, switch_type(3600, SwitchType::Tier(0)));
+
+        // Going down from low into the tier below it and back up
+        assert_eq!(SwitchType::Tier(1), switch_type(450, SwitchType::Low));
+        assert_eq!(SwitchType::Tier(1), switch_type(550, SwitchType::Tier(1)));
+        assert_eq!(SwitchType::Low, switch_type(700, SwitchType::Tier(1)));
+        assert_eq!(SwitchType::Low, switch_type(900, SwitchType::Low));
+        assert_eq!(SwitchType::Tier(0), switch_type(1100, SwitchType::Low));
+    }
+
+    #[test]
+    fn dwell_by_tier_name() {
+        let triggers: Triggers = serde_json::from_value(json!({
+            "dwell": { "tier": 5, "tiers": { "critical": 20 } },
+            "tiers": [
+                { "name": "degraded", "scene": "degraded", "bitrate": 3000 },
+                { "name": "critical", "scene": "critical", "bitrate": 500 }
+            ]
+        }))
+        .unwrap();
+
+        let dwell = |switch_type| triggers.dwell.get(&switch_type, &triggers.tiers);
+
+        assert_eq!(Some(Duration::from_secs(5)), dwell(SwitchType::Tier(0)));
+        assert_eq!(Some(Duration::from_secs(20)), dwell(SwitchType::Tier(1)));
+        assert_eq!(None, dwell(SwitchType::Tier(2)));
+    }
+
+    #[test]
+    fn triggers_without_tiers() {
+        let json = r#"{ "low": 800, "rtt": 2500, "offline": null }"#;
+
+        let triggers: Triggers = serde_json::from_str(json).unwrap();
+
+        assert!(triggers.tiers.is_empty());
+        assert_eq!(Some(800), triggers.low);
+    }
+
+    #[test]
+    fn recover_triggers() {
+        let triggers = Triggers {
+            low: Some(800),
+            low_recover: Some(1200),
+            rtt: Some(2500),
+            rtt_recover: Some(1000),
+            ..Default::default()
+        };
+
+        let stats = StreamStats {
+            bitrate: 1000,
+            rtt: Some(500.0),
+            ..Default::default()
+        };
+
+        assert_eq!(SwitchType::Normal, triggers.switch_type(&stats, None));
+        assert_eq!(
+            SwitchType::Low,
+            triggers.switch_type(&stats, Some(SwitchType::Low))
+        );
+
+        let stats = StreamStats {
+            bitrate: 1500,
+            rtt: Some(1800.0),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            SwitchType::Normal,
+            triggers.switch_type(&stats, Some(SwitchType::Normal))
+        );
+        assert_eq!(
+            SwitchType::Low,
+            triggers.switch_type(&stats, Some(SwitchType::Low))
+        );
+    }
+
+    #[test]
+    fn packet_triggers() {
+        let triggers = Triggers {
+            loss: Some(5),
+            loss_recover: Some(1),
+            links: Some(2),
+            links_recover: Some(3),
+            ..Default::default()
+        };
+
+        let stats = StreamStats {
+            bitrate: 5000,
+            loss_percent: Some(3.0),
+            ..Default::default()
+        };
+
+        assert_eq!(SwitchType::Normal, triggers.switch_type(&stats, None));
+        assert_eq!(
+            SwitchType::Low,
+            triggers.switch_type(&stats, Some(SwitchType::Low))
+        );
+
+        let link = |bitrate| stream_servers::stats::LinkStats {
+            addr: "10.0.0.1:5000".to_string(),
+            bitrate,
+            share: 0,
+        };
+
+        let stats = StreamStats {
+            bitrate: 5000,
+            links: Some(vec![link(2500), link(2500), link(0)]),
+            ..Default::default()
+        };
+
+        assert_eq!(SwitchType::Normal, triggers.switch_type(&stats, None));
+        assert_eq!(
+            SwitchType::Low,
+            triggers.switch_type(&stats, Some(SwitchType::Low))
+        );
+    }
+
+    #[test]
+    fn trigger_type_by_name() {
+        assert_eq!(Some(TriggerType::Loss), TriggerType::from_name("Loss"));
+        assert_eq!(
+            Some(TriggerType::DroppedRecover),
+            TriggerType::from_name("dropped").map(TriggerType::recover)
+        );
+        assert_eq!(None, TriggerType::from_name("800"));
+    }
 }
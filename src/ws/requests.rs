@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::{stream_servers, switcher};
+
 /// Message that will be received from a client
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RequestMessage {
@@ -18,6 +20,9 @@ pub struct RequestMessage {
 pub enum Request {
     Auth(Auth),
     SetPassword(SetPassword),
+    SetTriggers(switcher::TriggersUpdate),
+    SetDryRun(SetDryRun),
+    SetMockStats(SetMockStats),
     Me,
     Logout,
 }
@@ -35,6 +40,22 @@ pub struct SetPassword {
     pub password: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetDryRun {
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetMockStats {
+    /// Name of the mock stream server
+    pub name: String,
+
+    /// Goes back to the script when not set
+    pub stats: Option<stream_servers::mock::MockStats>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -98,4 +119,32 @@ mod tests {
 
         assert_eq!(expected, parsed);
     }
+
+    #[test]
+    fn set_triggers() {
+        let request = r#"{
+            "type": "setTriggers",
+            "low": 800,
+            "lowRecover": 1200,
+            "rtt": 2500,
+            "dwell": { "low": 10 }
+        }"#;
+
+        let parsed = serde_json::from_str::<Request>(request).unwrap();
+
+        println!("{:#?}", parsed);
+
+        let expected = Request::SetTriggers(switcher::TriggersUpdate {
+            low: Some(800),
+            low_recover: Some(1200),
+            rtt: Some(2500),
+            dwell: Some(switcher::Dwell {
+                low: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        assert_eq!(expected, parsed);
+    }
 }
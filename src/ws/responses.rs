@@ -1,6 +1,6 @@
 use serde::Serialize;
 
-use crate::config;
+use crate::{broadcasting_software::stats::SoftwareStats, config, state};
 
 /// Message that will be send to a client
 #[derive(Serialize)]
@@ -23,6 +23,9 @@ pub enum Response<'a> {
     SetPassword(SuccessfulLogin),
     Me(Me<'a>),
     UpdatedPassword,
+    UpdatedTriggers,
+    UpdatedDryRun,
+    UpdatedMockStats,
     Logout,
 }
 
@@ -34,6 +37,7 @@ pub enum ResponseError {
     AuthFailed,
     AuthorizationRequired,
     AlreadyAuthenticated,
+    MockServerNotFound,
 }
 
 #[derive(Debug, Serialize)]
@@ -42,8 +46,15 @@ pub struct SuccessfulLogin {
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Me<'a> {
     pub config: Config<'a>,
+
+    /// Status of the broadcasting software connections
+    pub connections: Vec<state::ConnectionStatus>,
+
+    /// Telemetry of the active broadcasting software when it's connected
+    pub software_stats: Option<SoftwareStats>,
 }
 
 /// Config details that will be send in the response
@@ -52,6 +63,10 @@ pub struct Me<'a> {
 pub struct Config<'a> {
     pub switcher: &'a config::Switcher,
     pub software: &'a config::SoftwareConnection,
+
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub mirrors: &'a [config::MirrorConnection],
+
     pub chat: &'a Option<config::Chat>,
     pub optional_scenes: &'a config::OptionalScenes,
     pub optional_options: &'a config::OptionalOptions,
@@ -62,6 +77,7 @@ impl<'a> Config<'a> {
         Self {
             switcher: &config.switcher,
             software: &config.software,
+            mirrors: &config.mirrors,
             chat: &config.chat,
             optional_scenes: &config.optional_scenes,
             optional_options: &config.optional_options,
@@ -120,6 +136,32 @@ mod tests {
         assert_eq!(expected, json);
     }
 
+    #[test]
+    fn me_response() {
+        let config: config::Config = serde_json::from_value(serde_json::json!({
+            "user": { "name": "test" },
+            "switcher": {},
+            "software": { "type": "Obs", "host": "localhost", "port": 4444 },
+            "optionalScenes": {},
+            "optionalOptions": {}
+        }))
+        .unwrap();
+
+        let me = Me {
+            config: Config::from(&config),
+            connections: Vec::new(),
+            software_stats: Some(SoftwareStats {
+                streaming_duration: Some(60),
+                ..Default::default()
+            }),
+        };
+
+        let json = serde_json::to_value(&me).unwrap();
+
+        assert_eq!(60, json["softwareStats"]["streamingDuration"]);
+        assert!(json.get("software_stats").is_none());
+    }
+
     #[test]
     fn no_nonce() {
         let message = ResponseMessage {
@@ -8,10 +8,10 @@ use tokio::sync::{
 use tokio_stream::wrappers::UnboundedReceiverStream;
 use tracing::debug;
 
-use crate::{user_manager, Noalbs};
+use crate::{switcher, user_manager, Noalbs};
 
 use super::{
-    requests::{Auth, SetPassword},
+    requests::{Auth, SetDryRun, SetMockStats, SetPassword},
     responses, InternalClientToken, WsClient, WsMessage,
 };
 
@@ -111,6 +111,9 @@ impl WsHandler {
 
         match &ws_message.message.request {
             Request::SetPassword(s) => self.set_password(s, &ws_message).await,
+            Request::SetTriggers(t) => self.set_triggers(t, &ws_message).await,
+            Request::SetDryRun(d) => self.set_dry_run(d, &ws_message).await,
+            Request::SetMockStats(m) => self.set_mock_stats(m, &ws_message).await,
             Request::Me => self.me(&ws_message).await,
             Request::Logout => self.logout(&ws_message).await,
             Request::Auth(_) => unreachable!(),
@@ -207,19 +210,79 @@ impl WsHandler {
         ws_message.reply(responses::Response::UpdatedPassword);
     }
 
-    async fn me(&self, ws_message: &WsMessage) {
+    async fn set_triggers(&self, update: &switcher::TriggersUpdate, ws_message: &WsMessage) {
         let lock = self.clients.read().await;
-        let user = lock
-            .get(&ws_message.internal_token)
-            .unwrap()
-            .user
-            .as_ref()
-            .unwrap();
+        let client = lock.get(&ws_message.internal_token).unwrap();
+
+        let user = client.user.as_ref().unwrap();
+
+        user.set_triggers(update.to_owned()).await;
+        let _ = user.save_config().await;
+
+        ws_message.reply(responses::Response::UpdatedTriggers);
+    }
+
+    async fn set_dry_run(&self, set_dry_run: &SetDryRun, ws_message: &WsMessage) {
+        let lock = self.clients.read().await;
+        let client = lock.get(&ws_message.internal_token).unwrap();
+
+        let user = client.user.as_ref().unwrap();
+
+        user.set_dry_run(set_dry_run.enabled).await;
+        let _ = user.save_config().await;
+
+        ws_message.reply(responses::Response::UpdatedDryRun);
+    }
+
+    async fn set_mock_stats(&self, set_mock_stats: &SetMockStats, ws_message: &WsMessage) {
+        let lock = self.clients.read().await;
+        let client = lock.get(&ws_message.internal_token).unwrap();
+
+        let user = client.user.as_ref().unwrap();
+
+        let found = user
+            .set_mock_stats(&set_mock_stats.name, set_mock_stats.stats.to_owned())
+            .await;
+
+        if !found {
+            ws_message.reply(responses::Response::Error(
+                responses::ResponseError::MockServerNotFound,
+            ));
+
+            return;
+        }
+
+        ws_message.reply(responses::Response::UpdatedMockStats);
+    }
+
+    async fn me(&self, ws_message: &WsMessage) {
+        // Not holding the clients lock while requesting the stats
+        let user = {
+            let lock = self.clients.read().await;
+            lock.get(&ws_message.internal_token)
+                .unwrap()
+                .user
+                .clone()
+                .unwrap()
+        };
+
+        let connection = user.state.read().await.broadcasting_software.connected();
+
+        // Not holding the state lock either, the software can be slow
+        let software_stats = match connection {
+            Some(bsc) => bsc.stats().await.ok(),
+            None => None,
+        };
 
         let state = user.state.read().await;
         let config = responses::Config::from(&state.config);
+        let connections = state.broadcasting_software.connection_statuses();
 
-        ws_message.reply(responses::Response::Me(responses::Me { config }));
+        ws_message.reply(responses::Response::Me(responses::Me {
+            config,
+            connections,
+            software_stats,
+        }));
     }
 
     async fn logout(&self, ws_message: &WsMessage) {